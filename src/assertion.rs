@@ -0,0 +1,182 @@
+use crate::error::Error;
+use crate::format::{dec, parse_size};
+use crate::printer::Printer;
+use crate::stats::Count;
+
+// A condition on the summary, such as `deleted>0` or `modified.bytes>10G`,
+// which makes snapdiff fail if it’s met.
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    text: String,
+    category: String,
+    measure: Measure,
+    op: Op,
+    value: u64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Measure {
+    Files,
+    Bytes,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Op {
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+    Equal,
+    NotEqual,
+}
+
+// Longer operators first, so that e.g. `>=` isn’t taken for `>`.
+const OPS: [(&str, Op); 7] = [
+    (">=", Op::GreaterOrEqual),
+    ("<=", Op::LessOrEqual),
+    ("!=", Op::NotEqual),
+    ("==", Op::Equal),
+    (">", Op::Greater),
+    ("<", Op::Less),
+    ("=", Op::Equal),
+];
+
+impl Assertion {
+    // Parses `CATEGORY[.files|.bytes]OP VALUE`, where `categories` are the
+    // known category names. The value may have a unit suffix (e.g. `10G`).
+    pub fn parse(s: &str, categories: &[&str]) -> Result<Assertion, Error> {
+        let invalid = |reason: &str| {
+            return Error::from(format!("invalid assertion: {}", s), reason.to_string());
+        };
+        let (pos, op_str, op) = OPS
+            .iter()
+            .filter_map(|(o, op)| s.find(o).map(|pos| (pos, *o, *op)))
+            .min_by_key(|(pos, o, _)| (*pos, usize::MAX - o.len()))
+            .ok_or_else(|| invalid("expected one of: > >= < <= = != "))?;
+        let metric = s[..pos].trim();
+        let value = s[pos + op_str.len()..].trim();
+        let (category, measure) = match metric.split_once('.') {
+            None => (metric, Measure::Files),
+            Some((c, "files")) => (c, Measure::Files),
+            Some((c, "bytes")) => (c, Measure::Bytes),
+            Some(_) => return Err(invalid("expected `.files` or `.bytes`")),
+        };
+        if !categories.contains(&category) {
+            return Err(invalid(&format!(
+                "unknown category, expected one of: {}",
+                categories.join(", ")
+            )));
+        }
+        let value = match measure {
+            Measure::Files => value.parse().ok(),
+            Measure::Bytes => parse_size(value),
+        }
+        .ok_or_else(|| invalid("invalid number"))?;
+        return Ok(Assertion {
+            text: s.to_string(),
+            category: category.to_string(),
+            measure,
+            op,
+            value,
+        });
+    }
+
+    // Whether the condition is met by the given category counts.
+    pub fn is_met(&self, counts: &[(&str, Count)]) -> bool {
+        let actual = self.actual(counts);
+        return match self.op {
+            Op::Greater => actual > self.value,
+            Op::GreaterOrEqual => actual >= self.value,
+            Op::Less => actual < self.value,
+            Op::LessOrEqual => actual <= self.value,
+            Op::Equal => actual == self.value,
+            Op::NotEqual => actual != self.value,
+        };
+    }
+
+    fn actual(&self, counts: &[(&str, Count)]) -> u64 {
+        let c = counts
+            .iter()
+            .find(|(name, _)| *name == self.category)
+            .map(|(_, c)| *c)
+            .unwrap_or(Count::new());
+        return match self.measure {
+            Measure::Files => c.files,
+            Measure::Bytes => c.size,
+        };
+    }
+}
+
+// Prints the assertions whose conditions are met, along with the actual
+// values.
+pub fn print_failed(printer: &mut dyn Printer, failed: &[&Assertion], counts: &[(&str, Count)]) {
+    let red = printer.colours().red;
+    let reset = printer.colours().reset;
+    let mut out = "\n".to_string();
+    for a in failed {
+        let unit = match a.measure {
+            Measure::Files => "files",
+            Measure::Bytes => "bytes",
+        };
+        out.push_str(&format!(
+            "{}Assertion failed: {} ({}: {} {}){}\n",
+            red,
+            a.text,
+            a.category,
+            dec(a.actual(counts) as i128),
+            unit,
+            reset
+        ));
+    }
+    printer.print(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::assertion::Assertion;
+    use crate::stats::Count;
+
+    const CATEGORIES: [&str; 2] = ["added", "deleted"];
+
+    fn counts(deleted_files: u64, deleted_size: u64) -> Vec<(&'static str, Count)> {
+        let mut c = Count::new();
+        c.add(deleted_files, deleted_size);
+        return vec![("added", Count::new()), ("deleted", c)];
+    }
+
+    #[test]
+    fn parses_assertions() {
+        for s in [
+            "deleted>0",
+            "deleted.files >= 1",
+            "deleted.bytes!=10K",
+            "added = 0",
+        ] {
+            assert!(Assertion::parse(s, &CATEGORIES).is_ok(), "{}", s);
+        }
+        for s in [
+            "deleted",
+            "foo>0",
+            "deleted.size>0",
+            "deleted>",
+            "deleted>1G",
+            "added<=-1",
+        ] {
+            assert!(Assertion::parse(s, &CATEGORIES).is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn evaluates_assertions() {
+        let a = Assertion::parse("deleted>0", &CATEGORIES).unwrap();
+        assert!(!a.is_met(&counts(0, 0)));
+        assert!(a.is_met(&counts(1, 0)));
+
+        let a = Assertion::parse("deleted.bytes>=10K", &CATEGORIES).unwrap();
+        assert!(!a.is_met(&counts(5, 9_999)));
+        assert!(a.is_met(&counts(1, 10_000)));
+
+        let a = Assertion::parse("added<=0", &CATEGORIES).unwrap();
+        assert!(a.is_met(&counts(1, 1)));
+    }
+}
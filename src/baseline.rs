@@ -0,0 +1,337 @@
+use crate::error::Error;
+use crate::file::{Kind, SizeBytes};
+use crate::glob::Glob;
+use crate::policy::{parse_config, Category};
+use crate::report::Report;
+use crate::stats::{withdraw_if, Count};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::path;
+
+// Changes that are known and acknowledged, e.g. rotated logs or
+// regenerated caches, so that only the unexpected changes are reported.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Baseline {
+    #[serde(default, rename = "expected")]
+    entries: Vec<Entry>,
+}
+
+// An entry applies to all changes where either path (as in snapshot 1 or
+// in snapshot 2) matches its glob.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Entry {
+    path: Glob,
+    // If not given, changes of all categories are expected.
+    categories: Option<Vec<Category>>,
+}
+
+impl Baseline {
+    // Parses a baseline file, which consists of `[[expected]]` tables, e.g.:
+    //
+    //     [[expected]]
+    //     path = "logs/*.log.*"
+    //     categories = ["added", "deleted"]
+    pub fn parse(text: &str) -> Result<Baseline, Error> {
+        return parse_config(text, "baseline");
+    }
+
+    fn is_expected(&self, category: &'static str, paths: &[&path::Path]) -> bool {
+        return self.entries.iter().any(|e| {
+            let is_category = e
+                .categories
+                .as_ref()
+                .map(|cs| cs.iter().any(|c| c.covers(category)))
+                .unwrap_or(true);
+            return is_category && paths.iter().any(|p| e.path.matches(p));
+        });
+    }
+
+    // Moves all expected changes out of the report, and returns their
+    // counts by category (in the order of `policy::CATEGORIES`).
+    pub fn apply(&self, r: &mut Report) -> Vec<(&'static str, Count)> {
+        let mut counts: Vec<(&'static str, Count)> = vec![];
+        let mut tally = |category: &'static str, files: u64, size: SizeBytes| {
+            if let Some((_, c)) = counts.iter_mut().find(|(c, _)| *c == category) {
+                c.add(files, size);
+                return;
+            }
+            let mut c = Count::new();
+            c.add(files, size);
+            counts.push((category, c));
+        };
+
+        // Folder moves are only expected as a whole.
+        let dir_moves = withdraw_if(&mut r.dir_moves, |d| {
+            return d
+                .files
+                .iter()
+                .all(|f2| self.is_expected("moved", &[&d.source(f2), &f2.path]));
+        });
+        for d in dir_moves {
+            r.moved.count.remove(d.count.files, d.count.size);
+            tally("moved", d.count.files, d.count.size);
+        }
+        // Respelled files are counted as moved, too.
+        for (_, f2) in r
+            .moved
+            .withdraw_if(|(f1, f2)| self.is_expected("moved", &[&f1.path, &f2.path]))
+        {
+            tally("moved", 1, f2.size);
+        }
+        for (_, f2) in r
+            .respelled
+            .withdraw_if(|(f1, f2)| self.is_expected("moved", &[&f1.path, &f2.path]))
+        {
+            r.moved.count.remove(1, f2.size);
+            tally("moved", 1, f2.size);
+        }
+
+        for f in r
+            .copied
+            .withdraw_if(|f| self.is_expected("copied", &[&f.path]))
+        {
+            tally("copied", 1, f.size);
+        }
+        for (category, stats, empty) in [
+            ("added", &mut r.added, &mut r.added_empty),
+            ("deleted", &mut r.deleted, &mut r.deleted_empty),
+        ] {
+            for f in stats.withdraw_if(|f| self.is_expected(category, &[&f.path])) {
+                if f.kind == Kind::Regular && f.size == 0 {
+                    empty.remove(1, 0);
+                }
+                tally(category, 1, f.size);
+            }
+        }
+
+        // Suspicious files might be corrupted, so they are never expected
+        // (and remain counted as modified).
+        let suspicious: HashSet<&path::Path> = r
+            .suspicious
+            .files()
+            .into_iter()
+            .flatten()
+            .map(|f| f.path.as_path())
+            .collect();
+        // Both lists of modified files are in the same order.
+        let is_expected: Vec<bool> = r
+            .modified_snap_1
+            .files()
+            .into_iter()
+            .flatten()
+            .zip(r.modified_snap_2.files().into_iter().flatten())
+            .map(|(f1, f2)| {
+                return !suspicious.contains(f2.path.as_path())
+                    && self.is_expected("modified", &[&f1.path, &f2.path]);
+            })
+            .collect();
+        let mut flags = is_expected.iter();
+        r.modified_snap_1.withdraw_if(|_| *flags.next().unwrap());
+        let mut flags = is_expected.iter();
+        let modified = r.modified_snap_2.withdraw_if(|_| *flags.next().unwrap());
+        for f2 in modified {
+            if let Some(changed) = r.changed_bytes.remove(&f2.path) {
+                r.changed_bytes_total = r.changed_bytes_total.map(|t| t - changed);
+            }
+            tally("modified", 1, f2.size);
+        }
+
+        // Appended and truncated files are counted as modified, too.
+        for (category, pairs) in [
+            ("appended", &mut r.appended),
+            ("truncated", &mut r.truncated),
+        ] {
+            for (f1, f2) in
+                pairs.withdraw_if(|(f1, f2)| self.is_expected(category, &[&f1.path, &f2.path]))
+            {
+                r.modified_snap_1.count.remove(1, f1.size);
+                r.modified_snap_2.count.remove(1, f2.size);
+                if category == "appended" {
+                    r.changed_bytes_total = r.changed_bytes_total.map(|t| t - (f2.size - f1.size));
+                }
+                tally(category, 1, f2.size);
+            }
+        }
+
+        for p in withdraw_if(&mut r.moved_modified, |p| {
+            return self.is_expected("moved_modified", &[&p.f1.path, &p.f2.path]);
+        }) {
+            r.moved_modified_snap_1.remove(1, p.f1.size);
+            r.moved_modified_snap_2.remove(1, p.f2.size);
+            tally("moved_modified", 1, p.f2.size);
+        }
+        for (f1, f2) in r
+            .relocated
+            .withdraw_if(|(f1, f2)| self.is_expected("relocated", &[&f1.path, &f2.path]))
+        {
+            r.relocated_snap_1.remove(1, f1.size);
+            tally("relocated", 1, f2.size);
+        }
+        for (_, size) in r
+            .type_changed
+            .withdraw_if(|p| self.is_expected("type_changed", &[p]))
+        {
+            tally("type_changed", 1, size);
+        }
+        for (_, f2) in r.metadata_changed.withdraw_if(|(f1, f2)| {
+            return self.is_expected("metadata_changed", &[&f1.path, &f2.path]);
+        }) {
+            tally("metadata_changed", 1, f2.size);
+        }
+        for (_, f2) in r.xattrs_changed.withdraw_if(|(f1, f2)| {
+            return self.is_expected("xattrs_changed", &[&f1.path, &f2.path]);
+        }) {
+            tally("xattrs_changed", 1, f2.size);
+        }
+        return counts;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::baseline::Baseline;
+    use crate::file::File;
+    use crate::report::Report;
+
+    #[test]
+    fn parses_baselines() {
+        assert!(Baseline::parse("[[expected]]\npath = \"logs/\"").is_ok());
+        for text in [
+            "[[expected]]\ncategories = [\"added\"]",
+            "[[expected]]\npath = \"a/\"\ncategories = [\"rotated\"]",
+            "[[expected]]\npath = \"a/\"\ncategories = \"added\"",
+            "[[rule]]\npath = \"a/\"",
+            "[expected]\npath = \"a/\"",
+            "path = \"a/\"",
+        ] {
+            assert!(Baseline::parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn withdraws_expected_changes() {
+        let baseline = Baseline::parse(
+            r#"
+            [[expected]]
+            path = "logs/*.log.*"
+            categories = ["added", "deleted"]
+
+            [[expected]]
+            path = "cache/"
+            "#,
+        )
+        .unwrap();
+
+        let mut r = Report::new().with_modified_snap_1_files();
+        r.added.record(&File::from_strings("logs/app.log.2", "abc"));
+        r.added.record(&File::from_strings("logs/app.log", "abc"));
+        r.deleted
+            .record(&File::from_strings("logs/app.log.1", "ab"));
+        r.modified_snap_1
+            .record(&File::from_strings("cache/x", "1"));
+        r.modified_snap_2
+            .record(&File::from_strings("cache/x", "12"));
+        r.modified_snap_1.record(&File::from_strings("data/y", "1"));
+        r.modified_snap_2
+            .record(&File::from_strings("data/y", "123"));
+        r.modified_snap_1
+            .record(&File::from_strings("cache/z", "1"));
+        r.modified_snap_2
+            .record(&File::from_strings("cache/z", "2"));
+        r.suspicious.record(&File::from_strings("cache/z", "2"));
+        r.appended.record(
+            &File::from_strings("logs/app.log.3", "1"),
+            &File::from_strings("logs/app.log.3", "12"),
+        );
+        r.modified_snap_1.count.add(1, 1);
+        r.modified_snap_2.count.add(1, 2);
+        r.xattrs_changed.record(
+            &File::from_strings("cache/w", "w"),
+            &File::from_strings("cache/w", "w"),
+        );
+        r.xattrs_changed.record(
+            &File::from_strings("data/v", "v"),
+            &File::from_strings("data/v", "v"),
+        );
+
+        let expected = baseline.apply(&mut r);
+        let expected: Vec<(&str, u64, u64)> = expected
+            .iter()
+            .map(|(c, count)| (*c, count.files, count.size))
+            .collect();
+        assert_eq!(
+            expected,
+            vec![
+                ("added", 1, 3),
+                ("deleted", 1, 2),
+                ("modified", 1, 2),
+                ("xattrs_changed", 1, 1)
+            ]
+        );
+        assert_eq!(r.xattrs_changed.count.files, 1);
+        assert_eq!(r.added.count.files, 1);
+        assert_eq!(
+            r.added.files().unwrap()[0].path.to_str(),
+            Some("logs/app.log")
+        );
+        assert_eq!(r.deleted.count.files, 0);
+        assert_eq!(r.modified_snap_2.files().unwrap().len(), 2);
+        assert_eq!(
+            r.modified_snap_1.files().unwrap()[0].path.to_str(),
+            Some("data/y")
+        );
+        // The suspicious file remains, since it might be corrupted.
+        assert_eq!(
+            r.modified_snap_2.files().unwrap()[1].path.to_str(),
+            Some("cache/z")
+        );
+        // The appended file remains, since only additions and deletions of
+        // rotated logs are expected.
+        assert_eq!(r.modified_snap_2.count.files, 3);
+        assert_eq!(r.modified_snap_2.count.size, 6);
+        assert_eq!(r.suspicious.count.files, 1);
+        assert_eq!(r.appended.count.files, 1);
+    }
+
+    #[test]
+    fn expects_broken_down_categories() {
+        let baseline = Baseline::parse(
+            r#"
+            [[expected]]
+            path = "logs/"
+            categories = ["modified"]
+            "#,
+        )
+        .unwrap();
+
+        let mut r = Report::new().with_modified_snap_1_files();
+        r.appended.record(
+            &File::from_strings("logs/1.log", "1"),
+            &File::from_strings("logs/1.log", "12"),
+        );
+        r.truncated.record(
+            &File::from_strings("logs/2.log", "123"),
+            &File::from_strings("logs/2.log", "1"),
+        );
+        r.relocated.record(
+            &File::from_strings("logs/3.log", "3"),
+            &File::from_strings("logs/old/3.log", "4"),
+        );
+        r.modified_snap_1.count.add(2, 4);
+        r.modified_snap_2.count.add(2, 3);
+
+        let expected = baseline.apply(&mut r);
+        let expected: Vec<(&str, u64, u64)> = expected
+            .iter()
+            .map(|(c, count)| (*c, count.files, count.size))
+            .collect();
+        assert_eq!(expected, vec![("appended", 1, 2), ("truncated", 1, 1)]);
+        assert_eq!(r.appended.count.files, 0);
+        assert_eq!(r.truncated.count.files, 0);
+        assert_eq!(r.modified_snap_2.count.files, 0);
+        // Relocated files are not covered by "modified".
+        assert_eq!(r.relocated.count.files, 1);
+    }
+}
@@ -22,7 +22,74 @@ impl CheckSummer {
         return self;
     }
 
+    // The checksum of the data consumed so far, without resetting.
+    pub fn peek(&self) -> CheckSum {
+        return self.context.clone().finalize();
+    }
+
     pub fn finalize(&mut self) -> CheckSum {
         return std::mem::replace(&mut self.context, CRC64.digest()).finalize();
     }
 }
+
+// Computes one checksum per block of the given size, so that two versions of
+// a file can be compared block by block later on.
+pub struct BlockSummer {
+    block_size: u64,
+    current: CheckSummer,
+    filled: u64,
+    check_sums: Vec<CheckSum>,
+}
+
+impl BlockSummer {
+    pub fn new(block_size: u64) -> BlockSummer {
+        return BlockSummer {
+            block_size,
+            current: CheckSummer::new(),
+            filled: 0,
+            check_sums: vec![],
+        };
+    }
+
+    pub fn consume(&mut self, mut bs: &[u8]) {
+        while !bs.is_empty() {
+            let n = bs.len().min((self.block_size - self.filled) as usize);
+            self.current.consume(&bs[..n]);
+            self.filled += n as u64;
+            bs = &bs[n..];
+            if self.filled == self.block_size {
+                self.check_sums.push(self.current.finalize());
+                self.filled = 0;
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> Vec<CheckSum> {
+        if self.filled > 0 {
+            self.check_sums.push(self.current.finalize());
+        }
+        return self.check_sums;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::checksum::{BlockSummer, CheckSummer};
+
+    #[test]
+    fn sums_blocks() {
+        let mut s = BlockSummer::new(4);
+        s.consume(b"abc");
+        s.consume(b"defgh");
+        s.consume(b"ij");
+        assert_eq!(
+            s.finalize(),
+            vec![
+                CheckSummer::new().consume(b"abcd").finalize(),
+                CheckSummer::new().consume(b"efgh").finalize(),
+                CheckSummer::new().consume(b"ij").finalize(),
+            ]
+        );
+        assert_eq!(BlockSummer::new(4).finalize(), vec![]);
+    }
+}
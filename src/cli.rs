@@ -1,20 +1,39 @@
-use crate::filter::Filter;
+use crate::assertion::Assertion;
+use crate::baseline::Baseline;
+use crate::filter::{Filter, SymlinkPolicy};
+use crate::path_key::{Mapping, PathKeys, UnicodeForm};
+use crate::policy::Policy;
 use crate::printer::{FilePrinter, TerminalPrinter};
+use crate::relocation::RelocateBy;
+use crate::report::Report;
+use crate::scope::Scope;
+use crate::snapper::Capture;
+use crate::snapshot_2;
+use crate::top::TopBy;
 use crate::Error;
-use clap::Parser;
+use clap::{ArgAction, Parser};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::{fs, path};
 
 pub struct Cli {
-    pub snap1_root: path::PathBuf,
-    pub snap2_root: path::PathBuf,
+    // The snapshot roots, from the oldest to the most recent one.
+    pub snap_roots: Vec<path::PathBuf>,
+    // Whether to print a timeline, instead of comparing two snapshots.
+    pub is_timeline: bool,
+    pub scope: Scope,
+    pub path_keys: PathKeys,
     pub filters: Filter,
+    pub snapshot_options: snapshot_2::Options,
+    pub capture: Capture,
     pub workers1: usize,
     pub workers2: usize,
     pub terminal_printer: TerminalPrinter,
     pub file_printer: Option<FilePrinter>,
+    pub json_printer: Option<FilePrinter>,
+    pub csv_printer: Option<FilePrinter>,
+    pub assertions: Vec<Assertion>,
     pub ctrl_c: CtrlCSignal,
 }
 
@@ -27,6 +46,10 @@ impl Clone for CtrlCSignal {
 }
 
 impl CtrlCSignal {
+    pub fn new() -> CtrlCSignal {
+        return CtrlCSignal(Arc::new(AtomicBool::new(false)));
+    }
+
     pub fn has_triggered(&self) -> bool {
         return self.0.load(Ordering::SeqCst);
     }
@@ -39,22 +62,42 @@ impl CtrlCSignal {
 /// - Identical: both snapshots contain a file at the same path with the same contents.
 /// - Moved:     both snapshots contain a file with the same contents, but at different
 ///              paths.
-/// - Added:     the second snapshot contains a file whose path or contents is not
+/// - Copied:    the second snapshot contains an additional file whose contents is
+///              present in the first snapshot (e.g., a duplicate of an identical file).
+/// - Added:     the second snapshot contains a file whose path and contents are not
 ///              present in the first snapshot.
 /// - Deleted:   the first snapshot contains a file whose path or contents is not
 ///              present in the second snapshot.
 /// - Modified:  both snapshots contain a file at the same path, but with different
-///              contents.
+///              contents. If neither the size nor the modification time of the file changed,
+///              it’s flagged as “Suspicious”, since this might indicate data corruption. In
+///              that case, snapdiff exits with code 3.
+/// - Moved+Modified (only with `--similarity`): a deleted and an added file whose
+///              contents is similar.
+/// - Relocated (only with `--relocated`): a deleted and an added file with the same
+///              name, but different contents.
+/// - Type changed: both snapshots contain an entry at the same path, but of a
+///              different type (e.g., a file was replaced by a folder or a symlink).
+/// - Expected (only with `--baseline`): changes of any of the above categories
+///              that are acknowledged in the baseline file.
+///
+/// Given more than two snapshots (or a single folder whose sub-folders are the
+/// snapshots, sorted by name), snapdiff prints a timeline instead, with the added,
+/// deleted and modified files between each pair of consecutive snapshots.
+///
+/// Exit codes: 0 if the snapshots are the same, 1 if they differ, 2 in case of an
+/// error, 3 if there are suspicious files, 4 if a `--fail-if` condition is met, 5 if
+/// the `--policy` is violated.
+// The list items are aligned for `--help`, which shows them verbatim.
+#[allow(clippy::doc_overindented_list_items)]
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, verbatim_doc_comment)]
 struct Args {
-    /// Path to the first snapshot (the older one).
-    #[arg(verbatim_doc_comment)]
-    snap1_path: String,
-
-    /// Path to the second snapshot (the more recent one).
-    #[arg(verbatim_doc_comment)]
-    snap2_path: String,
+    /// Paths to the snapshots, from the oldest to the most
+    /// recent one. A single path is taken as a folder whose
+    /// sub-folders are the snapshots (sorted by name).
+    #[arg(value_name = "SNAPS", required = true, verbatim_doc_comment)]
+    snap_paths: Vec<String>,
 
     /// Print a detailed report to a file. The report lists
     /// all captured file names (one per line, for all but
@@ -62,6 +105,254 @@ struct Args {
     #[arg(long = "report", short = 'r', verbatim_doc_comment)]
     report_file: Option<String>,
 
+    /// Write the summary (and its breakdowns, such as the ones
+    /// from `--group-by-dir`) as JSON to a file.
+    #[arg(long = "json", value_name = "PATH", verbatim_doc_comment)]
+    json_file: Option<String>,
+
+    /// Write the summary (and its breakdowns, such as the ones
+    /// from `--group-by-dir`) as CSV to a file. Each line holds
+    /// the files and bytes of one category.
+    #[arg(long = "csv", value_name = "PATH", verbatim_doc_comment)]
+    csv_file: Option<String>,
+
+    /// Only compare the given sub-directory (relative to the
+    /// snapshot roots). The paths in the report are still
+    /// relative to the snapshot roots.
+    #[arg(long = "subpath", value_name = "REL_PATH", verbatim_doc_comment)]
+    subpath: Option<String>,
+
+    /// Only compare the paths listed in the given file. The
+    /// paths must be relative to the snapshot roots, and they
+    /// are either separated by newlines or by NUL characters.
+    /// Folders in the list are compared in their entirety.
+    /// Listed paths are filtered in the same way as during a
+    /// full scan (e.g., dot paths and symlinks).
+    #[arg(
+        long = "files-from",
+        value_name = "FILE",
+        conflicts_with = "subpath",
+        verbatim_doc_comment
+    )]
+    files_from: Option<String>,
+
+    /// Rewrite paths of the first snapshot before comparing,
+    /// e.g. `--map Clients=Customers` compares the folder
+    /// `Clients/` of the first snapshot with the folder
+    /// `Customers/` of the second snapshot, as if it had
+    /// never been renamed. Can be specified multiple times.
+    #[arg(
+        long = "map",
+        value_name = "OLD=NEW",
+        action = ArgAction::Append,
+        verbatim_doc_comment
+    )]
+    map: Vec<String>,
+
+    /// Like `--map`, but using a regular expression, which is
+    /// matched against the entire path, e.g.
+    /// `--map-regex '^(\d{4})-(\d{2})/=$1/$2/'`. The first
+    /// matching rule is applied, where `--map` rules take
+    /// precedence over `--map-regex` rules.
+    #[arg(
+        long = "map-regex",
+        value_name = "PATTERN=REPLACEMENT",
+        action = ArgAction::Append,
+        verbatim_doc_comment
+    )]
+    map_regex: Vec<String>,
+
+    /// Consider paths as equivalent if they only differ in
+    /// their Unicode normalisation form (e.g., when files were
+    /// copied from macOS). Paths are reported in their original
+    /// spelling, and files whose paths are spelled differently
+    /// are counted as moved (“respelled”).
+    #[arg(
+        long = "normalize-unicode",
+        alias = "normalise-unicode",
+        value_name = "FORM",
+        verbatim_doc_comment
+    )]
+    normalize_unicode: Option<UnicodeForm>,
+
+    /// Consider paths as equivalent if they only differ in
+    /// upper or lower case. Works like `--normalize-unicode`
+    /// otherwise.
+    #[arg(long = "ignore-case", default_value_t = false, verbatim_doc_comment)]
+    ignore_case: bool,
+
+    /// Report a folder as moved as a whole, if at least the
+    /// given share of its files (in percent) was moved to the
+    /// same new folder. The files are not listed individually
+    /// in the report then. The default is `100`.
+    #[arg(
+        long = "dir-move-threshold",
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u64).range(1..=100),
+        verbatim_doc_comment
+    )]
+    dir_move_threshold: Option<u64>,
+
+    /// Don’t report folders as moved, but list all moved
+    /// files individually.
+    #[arg(
+        long = "no-dir-moves",
+        default_value_t = false,
+        conflicts_with = "dir_move_threshold",
+        verbatim_doc_comment
+    )]
+    no_dir_moves: bool,
+
+    /// Only detect moved or copied files by their contents if
+    /// they have at least the given size (in bytes). Smaller
+    /// files are reported as added or deleted instead. The
+    /// default is `1`, i.e. only empty files are excluded.
+    #[arg(
+        long = "min-move-size",
+        value_name = "BYTES",
+        default_value_t = 1,
+        verbatim_doc_comment
+    )]
+    min_move_size: u64,
+
+    /// Estimate how many bytes of the modified files have
+    /// actually changed, by comparing both versions block by
+    /// block (with the given block size in bytes, which is
+    /// 10 MiB by default). The block checksums are computed
+    /// while the files are read anyway.
+    #[arg(
+        long = "block-changes",
+        value_name = "BLOCK_SIZE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "10485760",
+        value_parser = clap::value_parser!(u64).range(1..),
+        verbatim_doc_comment
+    )]
+    block_changes: Option<u64>,
+
+    /// Detect files that were moved and modified at the same
+    /// time, by comparing the contents of deleted and added
+    /// files. Pairs whose contents is at least the given share
+    /// (in percent) similar are reported as “Moved+Modified”.
+    /// This requires additional processing time.
+    #[arg(
+        long = "similarity",
+        value_name = "PERCENT",
+        value_parser = clap::value_parser!(u64).range(1..=100),
+        verbatim_doc_comment
+    )]
+    similarity: Option<u64>,
+
+    /// Report a deleted and an added file as “Relocated”, if
+    /// they have the same name, even though their contents is
+    /// different (e.g., because the file was moved to another
+    /// folder and re-saved). This is a heuristic, which only
+    /// applies to files that couldn’t be matched otherwise:
+    /// - `name`: the entire file names must be the same.
+    /// - `stem`: the file names must be the same, except for
+    ///   their extensions.
+    #[arg(long = "relocated", value_name = "MATCH", verbatim_doc_comment)]
+    relocated: Option<RelocateBy>,
+
+    /// Only report files as relocated, if their sizes differ
+    /// by at most the given share (in percent).
+    #[arg(
+        long = "relocated-size-tolerance",
+        value_name = "PERCENT",
+        requires = "relocated",
+        value_parser = clap::value_parser!(u64).range(0..=100),
+        verbatim_doc_comment
+    )]
+    relocated_size_tolerance: Option<u64>,
+
+    /// Compare the extended attributes of identical files
+    /// (including SELinux labels, and POSIX ACLs on Linux),
+    /// and report files whose attributes differ, with the names
+    /// of the changed attributes in the detailed report. Files
+    /// whose attributes cannot be read are skipped.
+    #[arg(long = "xattrs", default_value_t = false, verbatim_doc_comment)]
+    xattrs: bool,
+
+    /// Break down the summary categories by folder, with the
+    /// folders cut off at the given depth (relative to the
+    /// snapshot roots). The folders are sorted by the bytes
+    /// that were added, deleted or modified in them. Only
+    /// identical, moved, copied, added, deleted and modified
+    /// (including appended and truncated) files are broken
+    /// down; moved+modified, relocated, type-changed and
+    /// expected files are not.
+    #[arg(
+        long = "group-by-dir",
+        value_name = "DEPTH",
+        value_parser = clap::value_parser!(u64).range(1..),
+        verbatim_doc_comment
+    )]
+    group_by_dir: Option<u64>,
+
+    /// Break down the summary categories by file extension
+    /// (only the categories listed for `--group-by-dir`).
+    #[arg(long = "group-by-ext", default_value_t = false, verbatim_doc_comment)]
+    group_by_ext: bool,
+
+    /// Break down the summary categories by file type, which
+    /// is detected from the leading bytes of the files (e.g.,
+    /// `jpeg`, `mp4`, `sqlite`, `gzip`). Only the categories
+    /// listed for `--group-by-dir` are broken down.
+    #[arg(long = "group-by-type", default_value_t = false, verbatim_doc_comment)]
+    group_by_type: bool,
+
+    /// Show how the files of each category are distributed by
+    /// size, and by age (i.e., the time since the files were
+    /// last modified). Only the categories listed for
+    /// `--group-by-dir` are covered.
+    #[arg(long = "histograms", default_value_t = false, verbatim_doc_comment)]
+    histograms: bool,
+
+    /// List the N largest added, deleted and modified files
+    /// below the summary.
+    #[arg(long = "top", value_name = "N", verbatim_doc_comment)]
+    top: Option<usize>,
+
+    /// How to rank modified files for `--top`:
+    /// - `bytes`: by their size (the default).
+    /// - `delta`: by how much they have grown or shrunk.
+    #[arg(
+        long = "top-by",
+        value_name = "RANK",
+        requires = "top",
+        default_value = "bytes",
+        verbatim_doc_comment
+    )]
+    top_by: TopBy,
+
+    /// Fail (with exit code 4) if the given condition is met,
+    /// e.g. `deleted>0` or `modified.bytes>10G`. A condition
+    /// refers to the files (`.files`, the default) or bytes
+    /// (`.bytes`) of a category, as named in the JSON output
+    /// (e.g. `added`, `moved`, `type_changed`). Sizes may
+    /// have a unit (`K`, `M`, `G`, … or `Ki`, `Mi`, `Gi`, …).
+    #[arg(
+        long = "fail-if",
+        value_name = "CONDITION",
+        action = ArgAction::Append,
+        verbatim_doc_comment
+    )]
+    fail_if: Vec<String>,
+
+    /// Check all changes against the rules of a policy file
+    /// (see README), and fail (with exit code 5) if any rule
+    /// is violated. The violations are listed in the summary
+    /// and in the detailed report.
+    #[arg(long = "policy", value_name = "FILE", verbatim_doc_comment)]
+    policy: Option<String>,
+
+    /// Acknowledge known changes (e.g. rotated logs) that are
+    /// listed in a baseline file (see README). They are only
+    /// counted as “Expected”, and aren’t listed anywhere else.
+    #[arg(long = "baseline", value_name = "FILE", verbatim_doc_comment)]
+    baseline: Option<String>,
+
     /// Include files or folders whose name start with a dot,
     /// instead of ignoring them (which is the default). For
     /// dot-folders, it ignores the entire (sub-)directory
@@ -74,15 +365,28 @@ struct Args {
     )]
     include_dot_paths: bool,
 
-    /// Include symlinks, instead of ignoring them (which is
-    /// the default). If symlinks are included, it counts one
-    /// file per symlink, without increasing the byte count.
-    /// If the symlink target had been changed between snapshots,
-    /// it counts the symlink file as modified.
+    /// How to treat symlinks:
+    /// - `skip`:   ignore them (which is the default).
+    /// - `record`: count one file per symlink, without increasing
+    ///   the byte count. If the symlink target had been changed
+    ///   between snapshots, it counts the symlink as modified.
+    /// - `follow`: traverse symlinks as if they were the files or
+    ///   folders they point to. Symlinks that point to one of
+    ///   their own parent folders are skipped.
+    #[arg(
+        long = "symlinks",
+        value_name = "POLICY",
+        default_value = "skip",
+        verbatim_doc_comment
+    )]
+    symlinks: SymlinkPolicy,
+
+    /// Shorthand for `--symlinks=record`.
     #[arg(
         long = "include-symlinks",
         short = 's',
         default_value_t = false,
+        conflicts_with = "symlinks",
         verbatim_doc_comment
     )]
     include_symlinks: bool,
@@ -112,11 +416,70 @@ struct Args {
 impl Cli {
     pub fn new_from_env() -> Result<Cli, Error> {
         let args = Args::parse();
+        let snap_roots = get_snaps(&args.snap_paths)?;
+        let is_timeline = args.snap_paths.len() != 2;
+        if is_timeline {
+            check_timeline_args(&args)?;
+        }
         let (workers1, workers2) = num_workers(args.workers);
+        let scope = get_scope(&args.subpath, &args.files_from, &snap_roots)?;
         return Ok(Cli {
-            snap1_root: get_snap(&args.snap1_path)?,
-            snap2_root: get_snap(&args.snap2_path)?,
-            filters: Filter::new(args.include_symlinks, args.include_dot_paths),
+            snap_roots: snap_roots.clone(),
+            is_timeline,
+            scope,
+            path_keys: {
+                let mut mappings = vec![];
+                for m in &args.map {
+                    mappings.push(Mapping::parse_prefix(m)?);
+                }
+                for m in &args.map_regex {
+                    mappings.push(Mapping::parse_regex(m)?);
+                }
+                PathKeys::new(mappings, args.normalize_unicode, args.ignore_case)
+            },
+            snapshot_options: {
+                let mut o = snapshot_2::Options::new();
+                if args.no_dir_moves {
+                    o.dir_move_threshold = None;
+                } else if args.dir_move_threshold.is_some() {
+                    o.dir_move_threshold = args.dir_move_threshold;
+                }
+                o.min_move_size = args.min_move_size;
+                o.similarity_threshold = args.similarity;
+                o.relocate_by = args.relocated;
+                o.relocate_size_tolerance = args.relocated_size_tolerance;
+                o.block_size = args.block_changes;
+                o.group_by_dir = args.group_by_dir.map(|d| d as usize);
+                o.group_by_ext = args.group_by_ext;
+                o.group_by_type = args.group_by_type;
+                o.histograms = args.histograms;
+                o.top = args.top.map(|n| (n, args.top_by));
+                o.policy = read_config(&args.policy, "policy")?
+                    .map(|text| Policy::parse(&text))
+                    .transpose()?;
+                o.baseline = read_config(&args.baseline, "baseline")?
+                    .map(|text| Baseline::parse(&text))
+                    .transpose()?;
+                o.snap_1_root = Some(snap_roots[0].clone());
+                o
+            },
+            capture: {
+                let mut c = Capture::new();
+                c.sketches = args.similarity.is_some();
+                c.xattrs = args.xattrs;
+                c.follow_symlinks = args.symlinks == SymlinkPolicy::Follow;
+                c.file_types = args.group_by_type;
+                c.block_size = args.block_changes;
+                c
+            },
+            filters: Filter::new(
+                if args.include_symlinks {
+                    SymlinkPolicy::Record
+                } else {
+                    args.symlinks
+                },
+                args.include_dot_paths,
+            ),
             workers1,
             workers2,
             terminal_printer: if args.no_color {
@@ -124,34 +487,61 @@ impl Cli {
             } else {
                 TerminalPrinter::new()
             },
-            file_printer: if args.report_file.is_none() {
-                None
-            } else {
-                let f = &args.report_file.unwrap();
-                let p = path::Path::new(f);
-                if p.exists() {
-                    return Err(Error::new(format!(
-                        "report file already exists: {}",
-                        p.display()
-                    )));
+            file_printer: new_file_printer(&args.report_file, "report")?,
+            json_printer: new_file_printer(&args.json_file, "JSON")?,
+            csv_printer: new_file_printer(&args.csv_file, "CSV")?,
+            assertions: {
+                let counts = Report::new().summary_counts();
+                let categories: Vec<&str> = counts.iter().map(|(name, _)| *name).collect();
+                let mut assertions = vec![];
+                for s in &args.fail_if {
+                    assertions.push(Assertion::parse(s, &categories)?);
                 }
-                Some(FilePrinter::new(p)?)
+                assertions
             },
             ctrl_c: {
-                let ctrl_c = Arc::new(AtomicBool::new(false));
-                let c_arc = Arc::clone(&ctrl_c);
+                let ctrl_c = CtrlCSignal::new();
+                let c_arc = Arc::clone(&ctrl_c.0);
                 ctrlc::set_handler(move || {
                     c_arc.store(true, Ordering::SeqCst);
                 })
                 .map_err(|e| {
                     Error::from("failed to register ^C handler".to_string(), e.to_string())
                 })?;
-                CtrlCSignal(ctrl_c)
+                ctrl_c
             },
         });
     }
 }
 
+// Opens an output file, which must not exist yet.
+fn new_file_printer(f: &Option<String>, what: &str) -> Result<Option<FilePrinter>, Error> {
+    let p = match f {
+        Some(f) => path::Path::new(f),
+        None => return Ok(None),
+    };
+    if p.exists() {
+        return Err(Error::new(format!(
+            "{} file already exists: {}",
+            what,
+            p.display()
+        )));
+    }
+    return Ok(Some(FilePrinter::new(p)?));
+}
+
+// Reads a configuration file (if given).
+fn read_config(f: &Option<String>, what: &str) -> Result<Option<String>, Error> {
+    let f = match f {
+        Some(f) => f,
+        None => return Ok(None),
+    };
+    let text = fs::read_to_string(f).map_err(|e| {
+        return Error::from(format!("cannot read {}: {}", what, f), e.to_string());
+    })?;
+    return Ok(Some(text));
+}
+
 fn num_workers(ws: Option<Vec<usize>>) -> (usize, usize) {
     let cores = thread::available_parallelism().unwrap().get();
     return ws
@@ -184,3 +574,83 @@ fn get_snap(s: &String) -> Result<path::PathBuf, Error> {
     }
     return Ok(path::Path::new(s).to_path_buf());
 }
+
+// The snapshot roots, either as given, or the sub-folders of the given
+// folder (sorted by name, without dot-folders).
+fn get_snaps(paths: &[String]) -> Result<Vec<path::PathBuf>, Error> {
+    let roots: Vec<path::PathBuf> = paths.iter().map(get_snap).collect::<Result<_, _>>()?;
+    if roots.len() != 1 {
+        return Ok(roots);
+    }
+    let parent = &roots[0];
+    let entries = fs::read_dir(parent).map_err(|e| {
+        return Error::from(
+            format!("cannot open directory: {}", parent.display()),
+            e.to_string(),
+        );
+    })?;
+    let mut children = vec![];
+    for e in entries.flatten() {
+        let is_dot = e.file_name().to_string_lossy().starts_with('.');
+        if !is_dot && e.path().is_dir() {
+            children.push(e.path());
+        }
+    }
+    children.sort();
+    if children.len() < 2 {
+        return Err(Error::new(format!(
+            "folder does not contain at least two snapshots: {}",
+            parent.display()
+        )));
+    }
+    return Ok(children);
+}
+
+// Options that refer to a single comparison can’t be combined with a
+// timeline.
+fn check_timeline_args(args: &Args) -> Result<(), Error> {
+    for (is_set, name) in [
+        (args.json_file.is_some(), "--json"),
+        (args.csv_file.is_some(), "--csv"),
+        (args.group_by_dir.is_some(), "--group-by-dir"),
+        (args.group_by_ext, "--group-by-ext"),
+        (args.group_by_type, "--group-by-type"),
+        (args.histograms, "--histograms"),
+        (args.top.is_some(), "--top"),
+        (!args.fail_if.is_empty(), "--fail-if"),
+        (args.policy.is_some(), "--policy"),
+    ] {
+        if is_set {
+            return Err(Error::new(format!(
+                "{} cannot be used with a timeline (i.e. more than two snapshots)",
+                name
+            )));
+        }
+    }
+    return Ok(());
+}
+
+fn get_scope(
+    subpath: &Option<String>,
+    files_from: &Option<String>,
+    snaps: &[path::PathBuf],
+) -> Result<Scope, Error> {
+    if let Some(sub) = subpath {
+        let scope = Scope::new(vec![path::PathBuf::from(sub)])?;
+        let p = &scope.paths()[0];
+        if !snaps.iter().any(|s| s.join(p).exists()) {
+            return Err(Error::new(format!(
+                "subpath exists in no snapshot: {}",
+                sub
+            )));
+        }
+        return Ok(scope);
+    }
+    if let Some(f) = files_from {
+        let text = fs::read_to_string(f).map_err(|e| {
+            return Error::from(format!("cannot read file list: {}", f), e.to_string());
+        })?;
+        return Scope::new(Scope::parse_list(&text));
+    }
+    return Ok(Scope::whole());
+}
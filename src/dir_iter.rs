@@ -1,11 +1,14 @@
 use crate::error::Error;
-use crate::file::SizeBytes;
-use crate::filter::{Filter, MatchReason};
+use crate::file::{Dir, Kind, SizeBytes};
+use crate::filter::{Filter, MatchReason, SymlinkPolicy};
+use crate::meta::Meta;
 use crate::printer::TerminalPrinter;
 use crate::progress::Progress;
+use crate::scope::Scope;
 use crate::snapper::{open_file, CHUNK_SIZE};
 use crate::stats::Stats;
 use std::cmp::Ordering;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
 use std::{fs, path};
 
 pub struct DirIterator {
@@ -16,14 +19,20 @@ pub struct DirIterator {
     filters: Filter,
     skipped: SkippedStats,
     num_workers: usize,
+    // The (device, inode) pairs of all directories on the path from the
+    // root to the directory that is currently being scanned.
+    ancestors: Vec<(u64, u64)>,
+    dirs: Vec<Dir>,
 }
 
 impl DirIterator {
     // Traverses the `root` directory recursively, and collects all
-    // encountered files (except the ones that are filtered out).
+    // encountered files (except the ones that are filtered out). Only
+    // the parts of the tree that are within `scope` are visited.
     pub fn scan(
         num_workers: usize,
         root: &path::Path,
+        scope: &Scope,
         filters: Filter,
         progress: &mut Progress<TerminalPrinter>,
     ) -> Result<DirIterator, Error> {
@@ -36,28 +45,41 @@ impl DirIterator {
             filters,
             skipped: SkippedStats::new(),
             num_workers,
+            ancestors: vec![],
+            dirs: vec![],
         };
-        dir_it.scan_dir(root)?;
+        for p in scope.paths() {
+            if p.as_os_str().is_empty() {
+                dir_it.scan_dir(root)?;
+            } else {
+                dir_it.scan_scoped(p)?;
+            }
+        }
 
         // Only sort the “large” files, because for the “small” ones
         // the order doesn’t matter (as they fit into one chunk anyway).
-        dir_it.large_files.paths.sort_by(|(_, s1), (_, s2)| {
-            return if s1 > s2 {
-                Ordering::Less
-            } else if s1 < s2 {
-                Ordering::Greater
-            } else {
-                Ordering::Equal
-            };
-        });
+        dir_it
+            .large_files
+            .paths
+            .sort_by(|(_, _, s1, _), (_, _, s2, _)| {
+                return if s1 > s2 {
+                    Ordering::Less
+                } else if s1 < s2 {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                };
+            });
         progress.scan_done(dir_it.scheduled.count, dir_it.skipped);
         return Ok(dir_it);
     }
 
     fn scan_dir(&mut self, path: &path::Path) -> Result<(), Error> {
-        if !path.is_dir() {
-            return Err(Error::new(format!("not a directory: {}", path.display())));
-        }
+        let dir_id = fs::metadata(path)
+            .ok()
+            .filter(|m| m.is_dir())
+            .map(|m| (m.dev(), m.ino()))
+            .ok_or_else(|| Error::new(format!("not a directory: {}", path.display())))?;
         let read_dir_result = fs::read_dir(path).map_err(|e| {
             self.skipped.no_opener += 1;
             return Error::from(
@@ -65,9 +87,11 @@ impl DirIterator {
                 e.to_string(),
             );
         });
-        if !read_dir_result.is_ok() {
+        if read_dir_result.is_err() {
             return Ok(());
         }
+        self.ancestors.push(dir_id);
+        let recorded_before = self.recorded_entries();
         for read_res in read_dir_result? {
             let (p, name) = read_res
                 .map_err(|e| {
@@ -89,25 +113,155 @@ impl DirIterator {
             if shall_skip {
                 continue;
             }
-            if p.is_dir() {
+            if p.is_symlink() {
+                self.scan_symlink(p)?;
+            } else if p.is_dir() {
                 self.scan_dir(&p)?;
             } else if p.is_file() {
-                open_file(&p)
-                    .map(|f| {
-                        let m = f.metadata().expect("failed to query file metadata");
-                        self.push(p, m.len());
-                    })
-                    .unwrap_or_else(|_| {
-                        self.skipped.no_opener += 1;
-                    });
-            } else if p.is_symlink() {
-                self.push(p, 0);
+                self.push_file(p);
+            } else {
+                self.push_special(p);
             }
         }
+        self.ancestors.pop();
+        if path != self.root {
+            // Entries that were filtered out or couldn’t be opened don’t count.
+            let is_empty = self.recorded_entries() == recorded_before;
+            let rel_path = path.strip_prefix(&self.root).unwrap().to_path_buf();
+            self.dirs.push(Dir::new(rel_path, is_empty));
+        }
         return Ok(());
     }
 
-    fn push(&mut self, p: path::PathBuf, size: SizeBytes) {
+    // Processes a path that had been explicitly requested. Since the path
+    // might legitimately not exist in one of the snapshots, it’s ignored
+    // in that case. The filters and the symlink policy apply to the path
+    // and its intermediate folders as if the tree was traversed from the
+    // root, so that scoping never includes paths that a full scan skips.
+    fn scan_scoped(&mut self, rel_path: &path::Path) -> Result<(), Error> {
+        let depth = self.ancestors.len();
+        let res = self.scan_scoped_within(rel_path);
+        self.ancestors.truncate(depth);
+        return res;
+    }
+
+    fn scan_scoped_within(&mut self, rel_path: &path::Path) -> Result<(), Error> {
+        let root_id = fs::metadata(&self.root)
+            .ok()
+            .filter(|m| m.is_dir())
+            .map(|m| (m.dev(), m.ino()))
+            .ok_or_else(|| Error::new(format!("not a directory: {}", self.root.display())))?;
+        self.ancestors.push(root_id);
+        let mut p = self.root.clone();
+        let mut names = rel_path.iter().peekable();
+        while let Some(name) = names.next() {
+            p.push(name);
+            if !p.exists() && !p.is_symlink() {
+                return Ok(());
+            }
+            match self.filters.matches(&p, &name.to_os_string()) {
+                Some(MatchReason::IsSymlink) => {
+                    self.skipped.symlinks += 1;
+                    return Ok(());
+                }
+                Some(MatchReason::IsDotPath) => {
+                    self.skipped.dot_paths += 1;
+                    return Ok(());
+                }
+                None => {}
+            }
+            if names.peek().is_none() {
+                break;
+            }
+
+            // Intermediate symlinks are only traversed when following them.
+            if p.is_symlink() && self.filters.symlinks() != SymlinkPolicy::Follow {
+                self.skipped.symlinks += 1;
+                return Ok(());
+            }
+            let dir_id = match fs::metadata(&p) {
+                Ok(m) if m.is_dir() => (m.dev(), m.ino()),
+                _ => return Ok(()),
+            };
+            if self.ancestors.contains(&dir_id) {
+                self.skipped.symlink_loops += 1;
+                return Ok(());
+            }
+            self.ancestors.push(dir_id);
+        }
+        if p.is_symlink() {
+            self.scan_symlink(p)?;
+        } else if p.is_dir() {
+            self.scan_dir(&p)?;
+        } else if p.is_file() {
+            self.push_file(p);
+        } else {
+            self.push_special(p);
+        }
+        return Ok(());
+    }
+
+    fn scan_symlink(&mut self, p: path::PathBuf) -> Result<(), Error> {
+        match self.filters.symlinks() {
+            SymlinkPolicy::Skip => {}
+            SymlinkPolicy::Record => match fs::symlink_metadata(&p) {
+                Ok(m) => self.push(p, Kind::Symlink, 0, Meta::from(&m)),
+                Err(_) => self.skipped.no_opener += 1,
+            },
+            SymlinkPolicy::Follow => match fs::metadata(&p) {
+                Err(_) => self.skipped.no_opener += 1,
+                Ok(m) if m.is_dir() => {
+                    // A symlink that points to one of its own ancestors would
+                    // make the traversal recurse endlessly.
+                    if self.ancestors.contains(&(m.dev(), m.ino())) {
+                        self.skipped.symlink_loops += 1;
+                    } else {
+                        self.scan_dir(&p)?;
+                    }
+                }
+                Ok(m) if m.is_file() => self.push_file(p),
+                Ok(_) => self.push_special(p),
+            },
+        }
+        return Ok(());
+    }
+
+    fn push_file(&mut self, p: path::PathBuf) {
+        open_file(&p)
+            .map(|f| {
+                let m = f.metadata().expect("failed to query file metadata");
+                self.push(p, Kind::Regular, m.len(), Meta::from(&m));
+            })
+            .unwrap_or_else(|_| {
+                self.skipped.no_opener += 1;
+            });
+    }
+
+    // Records FIFOs, sockets and device nodes (which are never opened).
+    fn push_special(&mut self, p: path::PathBuf) {
+        let m = match fs::metadata(&p) {
+            Ok(m) => m,
+            Err(_) => {
+                self.skipped.no_opener += 1;
+                return;
+            }
+        };
+        let t = m.file_type();
+        let kind = if t.is_fifo() {
+            Kind::Fifo
+        } else if t.is_socket() {
+            Kind::Socket
+        } else if t.is_block_device() {
+            Kind::BlockDevice
+        } else if t.is_char_device() {
+            Kind::CharDevice
+        } else {
+            return;
+        };
+        self.push(p, kind, 0, Meta::from(&m));
+    }
+
+    fn push(&mut self, p: path::PathBuf, kind: Kind, size: SizeBytes, meta: Meta) {
         self.scheduled.count.add(1, size);
 
         // Sort into “small” and “large” internally files. That way, the “large”
@@ -115,20 +269,34 @@ impl DirIterator {
         // worker is left over hashing a large file towards the end, when there are
         // no files left for other workers to pick up anymore.
         if size > CHUNK_SIZE && self.num_workers > 1 {
-            self.large_files.paths.push((p.to_path_buf(), size));
+            self.large_files
+                .paths
+                .push((p.to_path_buf(), kind, size, meta));
         } else {
-            self.small_files.paths.push((p.to_path_buf(), size));
+            self.small_files
+                .paths
+                .push((p.to_path_buf(), kind, size, meta));
         }
     }
 
-    pub fn next_file(&mut self) -> Option<path::PathBuf> {
+    // The number of files and directories that were recorded so far.
+    fn recorded_entries(&self) -> u64 {
+        return self.scheduled.count.files + self.dirs.len() as u64;
+    }
+
+    // Hands out all encountered directories (relative to the root).
+    pub fn take_dirs(&mut self) -> Vec<Dir> {
+        return std::mem::take(&mut self.dirs);
+    }
+
+    pub fn next_file(&mut self) -> Option<(path::PathBuf, Kind, Meta)> {
         return self.large_files.next().or_else(|| self.small_files.next());
     }
 }
 
 // An iterable list of file paths.
 struct PathList {
-    paths: Vec<(path::PathBuf, u64)>,
+    paths: Vec<(path::PathBuf, Kind, u64, Meta)>,
     it: usize,
 }
 
@@ -140,13 +308,13 @@ impl PathList {
         };
     }
 
-    fn next(&mut self) -> Option<path::PathBuf> {
+    fn next(&mut self) -> Option<(path::PathBuf, Kind, Meta)> {
         if self.it >= self.paths.len() {
             return None;
         }
-        let (p, _) = &self.paths[self.it];
+        let (p, kind, _, meta) = &self.paths[self.it];
         self.it += 1;
-        return Some(p.to_path_buf());
+        return Some((p.to_path_buf(), *kind, *meta));
     }
 }
 
@@ -154,6 +322,7 @@ impl PathList {
 pub struct SkippedStats {
     pub dot_paths: u64,
     pub symlinks: u64,
+    pub symlink_loops: u64,
     pub no_opener: u64,
 }
 
@@ -162,7 +331,108 @@ impl SkippedStats {
         return SkippedStats {
             dot_paths: 0,
             symlinks: 0,
+            symlink_loops: 0,
             no_opener: 0,
         };
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dir_iter::DirIterator;
+    use crate::filter::{Filter, SymlinkPolicy};
+    use crate::printer::TerminalPrinter;
+    use crate::progress::Progress;
+    use crate::scope::Scope;
+    use std::fs;
+    use std::os::unix::fs::symlink;
+    use std::path::PathBuf;
+
+    fn scan_scoped(symlinks: SymlinkPolicy) -> (Vec<PathBuf>, DirIterator) {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("sub/.hidden")).unwrap();
+        fs::write(root.path().join("sub/a"), "a").unwrap();
+        fs::write(root.path().join("sub/.hidden/b"), "b").unwrap();
+        symlink(root.path().join("sub"), root.path().join("link")).unwrap();
+        let scope = Scope::new(vec![
+            PathBuf::from("sub/a"),
+            PathBuf::from("sub/.hidden/b"),
+            PathBuf::from("link/a"),
+            PathBuf::from("missing/a"),
+        ])
+        .unwrap();
+        let mut progress = Progress::new(TerminalPrinter::new_plain(), "Snap", None);
+        let mut dir_it = DirIterator::scan(
+            1,
+            root.path(),
+            &scope,
+            Filter::new(symlinks, false),
+            &mut progress,
+        )
+        .unwrap();
+        let mut paths = vec![];
+        while let Some((p, _, _)) = dir_it.next_file() {
+            paths.push(p.strip_prefix(root.path()).unwrap().to_path_buf());
+        }
+        paths.sort();
+        return (paths, dir_it);
+    }
+
+    #[test]
+    fn filters_scoped_paths() {
+        let (paths, dir_it) = scan_scoped(SymlinkPolicy::Skip);
+        assert_eq!(paths, vec![PathBuf::from("sub/a")]);
+        assert_eq!(dir_it.skipped.dot_paths, 1);
+        assert_eq!(dir_it.skipped.symlinks, 1);
+
+        let (paths, dir_it) = scan_scoped(SymlinkPolicy::Record);
+        assert_eq!(paths, vec![PathBuf::from("sub/a")]);
+        assert_eq!(dir_it.skipped.symlinks, 1);
+    }
+
+    #[test]
+    fn follows_scoped_symlinks() {
+        let (paths, dir_it) = scan_scoped(SymlinkPolicy::Follow);
+        assert_eq!(paths, vec![PathBuf::from("link/a"), PathBuf::from("sub/a")]);
+        assert_eq!(dir_it.skipped.dot_paths, 1);
+        assert_eq!(dir_it.skipped.symlinks, 0);
+    }
+
+    #[test]
+    fn tells_empty_dirs() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("empty")).unwrap();
+        fs::create_dir_all(root.path().join("filtered")).unwrap();
+        fs::write(root.path().join("filtered/.hidden"), "").unwrap();
+        symlink(root.path().join("empty"), root.path().join("filtered/link")).unwrap();
+        fs::create_dir_all(root.path().join("nested/empty")).unwrap();
+        fs::create_dir_all(root.path().join("full")).unwrap();
+        fs::write(root.path().join("full/a"), "a").unwrap();
+
+        let mut progress = Progress::new(TerminalPrinter::new_plain(), "Snap", None);
+        let mut dir_it = DirIterator::scan(
+            1,
+            root.path(),
+            &Scope::whole(),
+            Filter::new(SymlinkPolicy::Skip, false),
+            &mut progress,
+        )
+        .unwrap();
+        let mut dirs: Vec<(PathBuf, bool)> = dir_it
+            .take_dirs()
+            .into_iter()
+            .map(|d| (d.path, d.is_empty))
+            .collect();
+        dirs.sort();
+        assert_eq!(
+            dirs,
+            vec![
+                (PathBuf::from("empty"), true),
+                (PathBuf::from("filtered"), true),
+                (PathBuf::from("full"), false),
+                (PathBuf::from("nested"), false),
+                (PathBuf::from("nested/empty"), true),
+            ]
+        );
+    }
+}
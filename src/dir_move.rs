@@ -0,0 +1,204 @@
+use crate::file::File;
+use crate::stats::Count;
+use std::collections::HashMap;
+use std::path;
+
+// A directory whose files were moved to another directory as a whole,
+// e.g. because the directory was renamed.
+#[derive(Debug)]
+pub struct DirMove {
+    pub from: path::PathBuf,
+    pub to: path::PathBuf,
+    pub count: Count,
+    // The moved files, as in snapshot 2.
+    pub files: Vec<File>,
+}
+
+impl DirMove {
+    // The path of a moved file in snapshot 1.
+    pub fn source(&self, f2: &File) -> path::PathBuf {
+        return self
+            .from
+            .join(f2.path.strip_prefix(&self.to).unwrap_or(&f2.path));
+    }
+}
+
+// Directories with fewer files are never reported as moved.
+const MIN_FILES: u64 = 2;
+
+// Splits up file moves into directory moves and the remaining individual
+// moves. A directory counts as moved if at least `threshold_percent` of
+// its files (as in snapshot 1) were moved to the same new directory. If
+// a directory doesn’t qualify, its sub-directories are considered instead.
+pub fn aggregate<F>(
+    moves: Vec<(File, File)>,
+    files_in_dir: F,
+    threshold_percent: u64,
+) -> (Vec<DirMove>, Vec<(File, File)>)
+where
+    F: Fn(&path::Path) -> u64,
+{
+    let mut groups: HashMap<(path::PathBuf, path::PathBuf), Vec<(File, File)>> = HashMap::new();
+    let mut individual_moves = vec![];
+    for (f1, f2) in moves {
+        match moved_dirs(&f1.path, &f2.path) {
+            Some(dirs) => groups.entry(dirs).or_default().push((f1, f2)),
+            None => individual_moves.push((f1, f2)),
+        }
+    }
+
+    let mut dir_moves = vec![];
+    let mut pending: Vec<_> = groups.into_iter().collect();
+    while let Some(((from, to), pairs)) = pending.pop() {
+        let moved = pairs.len() as u64;
+        if moved < MIN_FILES {
+            individual_moves.extend(pairs);
+            continue;
+        }
+        if moved * 100 >= files_in_dir(&from) * threshold_percent {
+            let mut count = Count::new();
+            for (_, f2) in &pairs {
+                count.add(1, f2.size);
+            }
+            let files = pairs.into_iter().map(|(_, f2)| f2).collect();
+            dir_moves.push(DirMove {
+                from,
+                to,
+                count,
+                files,
+            });
+            continue;
+        }
+        let mut sub_groups: HashMap<(path::PathBuf, path::PathBuf), Vec<(File, File)>> =
+            HashMap::new();
+        for (f1, f2) in pairs {
+            // Within a group, the paths only differ in the `from`/`to` part.
+            let rest = f1.path.strip_prefix(&from).unwrap();
+            if rest.components().count() < 2 {
+                individual_moves.push((f1, f2));
+                continue;
+            }
+            let next = rest.components().next().unwrap();
+            sub_groups
+                .entry((from.join(next), to.join(next)))
+                .or_default()
+                .push((f1, f2));
+        }
+        pending.extend(sub_groups);
+    }
+    dir_moves.sort_by(|d1, d2| d1.from.cmp(&d2.from));
+    return (dir_moves, individual_moves);
+}
+
+// Determines which directory was presumably moved where, by stripping
+// the common trailing path components. E.g., for `a/b/c.txt` and
+// `x/b/c.txt`, the result is `a` and `x`.
+fn moved_dirs(p1: &path::Path, p2: &path::Path) -> Option<(path::PathBuf, path::PathBuf)> {
+    let c1: Vec<path::Component> = p1.components().collect();
+    let c2: Vec<path::Component> = p2.components().collect();
+    let mut common = 0;
+    while common < c1.len() && common < c2.len() {
+        if c1[c1.len() - 1 - common] != c2[c2.len() - 1 - common] {
+            break;
+        }
+        common += 1;
+    }
+    if common == 0 {
+        return None;
+    }
+    let from: path::PathBuf = c1[..c1.len() - common].iter().collect();
+    let to: path::PathBuf = c2[..c2.len() - common].iter().collect();
+    if from.file_name().is_none() || to.file_name().is_none() {
+        return None;
+    }
+    return Some((from, to));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dir_move::{aggregate, moved_dirs};
+    use crate::file::File;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn determines_moved_dirs() {
+        assert_eq!(
+            moved_dirs(
+                Path::new("Clients/acme/a.txt"),
+                Path::new("Customers/acme/a.txt")
+            ),
+            Some((PathBuf::from("Clients"), PathBuf::from("Customers")))
+        );
+        assert_eq!(
+            moved_dirs(Path::new("a/b.txt"), Path::new("x/y/b.txt")),
+            Some((PathBuf::from("a"), PathBuf::from("x/y")))
+        );
+        assert_eq!(moved_dirs(Path::new("a/b.txt"), Path::new("a/c.txt")), None);
+        assert_eq!(moved_dirs(Path::new("a/b.txt"), Path::new("b.txt")), None);
+    }
+
+    #[test]
+    fn aggregates_dir_moves() {
+        let moves = vec![
+            (
+                File::from_strings("old/1.txt", "1"),
+                File::from_strings("new/1.txt", "1"),
+            ),
+            (
+                File::from_strings("old/sub/2.txt", "22"),
+                File::from_strings("new/sub/2.txt", "22"),
+            ),
+            (
+                File::from_strings("old/3.txt", "333"),
+                File::from_strings("elsewhere/3.txt", "333"),
+            ),
+            (
+                File::from_strings("x.txt", "x"),
+                File::from_strings("y.txt", "x"),
+            ),
+        ];
+        let files_in_dir = |p: &Path| if p == Path::new("old") { 4 } else { 1 };
+
+        let (dir_moves, individual_moves) = aggregate(moves.clone(), files_in_dir, 100);
+        assert_eq!(dir_moves.len(), 0);
+        assert_eq!(individual_moves.len(), 4);
+
+        let (dir_moves, individual_moves) = aggregate(moves, files_in_dir, 50);
+        assert_eq!(dir_moves.len(), 1);
+        assert_eq!(dir_moves[0].from, PathBuf::from("old"));
+        assert_eq!(dir_moves[0].to, PathBuf::from("new"));
+        assert_eq!(dir_moves[0].count.files, 2);
+        assert_eq!(dir_moves[0].count.size, 3);
+        assert_eq!(individual_moves.len(), 2);
+    }
+
+    #[test]
+    fn aggregates_sub_dir_moves() {
+        let moves = vec![
+            (
+                File::from_strings("old/1.txt", "1"),
+                File::from_strings("new/1.txt", "1"),
+            ),
+            (
+                File::from_strings("old/sub/2.txt", "22"),
+                File::from_strings("new/sub/2.txt", "22"),
+            ),
+            (
+                File::from_strings("old/sub/3.txt", "333"),
+                File::from_strings("new/sub/3.txt", "333"),
+            ),
+        ];
+        let files_in_dir = |p: &Path| match p.to_str().unwrap() {
+            "old" => 4,
+            "old/sub" => 2,
+            _ => 0,
+        };
+
+        let (dir_moves, individual_moves) = aggregate(moves, files_in_dir, 100);
+        assert_eq!(dir_moves.len(), 1);
+        assert_eq!(dir_moves[0].from, PathBuf::from("old/sub"));
+        assert_eq!(dir_moves[0].to, PathBuf::from("new/sub"));
+        assert_eq!(dir_moves[0].count.files, 2);
+        assert_eq!(individual_moves.len(), 1);
+    }
+}
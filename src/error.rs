@@ -1,6 +1,7 @@
 use std::fmt::{Display, Formatter};
 use std::io;
 
+#[derive(Debug)]
 pub struct Error {
     message: String,
     original: String,
@@ -21,7 +22,7 @@ impl Error {
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let original = if self.original != "" {
+        let original = if !self.original.is_empty() {
             format!("\n({})", self.original)
         } else {
             "".to_string()
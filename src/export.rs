@@ -0,0 +1,176 @@
+use crate::printer::Printer;
+use crate::stats::Count;
+
+// The machine-readable form of a report, which can be written as JSON or
+// as CSV. It consists of the summary, plus optional breakdowns of the
+// summary (e.g. by directory).
+pub struct Export {
+    summary: Vec<(&'static str, Count)>,
+    sections: Vec<Section>,
+}
+
+// A breakdown, where each row has a key (e.g. a directory path) and the
+// counts per category.
+pub struct Section {
+    name: &'static str,
+    key_name: &'static str,
+    rows: Vec<(String, Vec<(&'static str, Count)>)>,
+}
+
+impl Export {
+    pub fn new(summary: Vec<(&'static str, Count)>) -> Export {
+        return Export {
+            summary,
+            sections: vec![],
+        };
+    }
+
+    pub fn add_section(&mut self, s: Section) {
+        self.sections.push(s);
+    }
+
+    // Writes an object with the summary, and one array per section.
+    pub fn write_json(&self, printer: &mut dyn Printer) {
+        let mut out = "{\n  \"summary\": ".to_string();
+        out.push_str(&json_counts(&self.summary));
+        for s in &self.sections {
+            out.push_str(&format!(",\n  {}: [", json_string(s.name)));
+            for (i, (key, counts)) in s.rows.iter().enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                let mut entries =
+                    vec![format!("{}: {}", json_string(s.key_name), json_string(key))];
+                entries.extend(counts.iter().map(|(c, n)| json_count(c, n)));
+                out.push_str(&format!("{}\n    {{{}}}", separator, entries.join(", ")));
+            }
+            out.push_str(if s.rows.is_empty() { "]" } else { "\n  ]" });
+        }
+        out.push_str("\n}\n");
+        printer.print(out);
+    }
+
+    // Writes one line per count, where the summary has an empty section
+    // and key.
+    pub fn write_csv(&self, printer: &mut dyn Printer) {
+        let mut out = "section,key,category,files,bytes\n".to_string();
+        for (category, c) in &self.summary {
+            out.push_str(&format!("summary,,{},{},{}\n", category, c.files, c.size));
+        }
+        for s in &self.sections {
+            for (key, counts) in &s.rows {
+                for (category, c) in counts {
+                    out.push_str(&format!(
+                        "{},{},{},{},{}\n",
+                        s.name,
+                        csv_field(key),
+                        category,
+                        c.files,
+                        c.size
+                    ));
+                }
+            }
+        }
+        printer.print(out);
+    }
+}
+
+impl Section {
+    pub fn new(name: &'static str, key_name: &'static str) -> Section {
+        return Section {
+            name,
+            key_name,
+            rows: vec![],
+        };
+    }
+
+    pub fn add_row(&mut self, key: String, counts: Vec<(&'static str, Count)>) {
+        self.rows.push((key, counts));
+    }
+}
+
+fn json_counts(counts: &[(&'static str, Count)]) -> String {
+    let entries: Vec<String> = counts.iter().map(|(c, n)| json_count(c, n)).collect();
+    return format!("{{\n    {}\n  }}", entries.join(",\n    "));
+}
+
+fn json_count(category: &str, c: &Count) -> String {
+    return format!(
+        "{}: {{\"files\": {}, \"bytes\": {}}}",
+        json_string(category),
+        c.files,
+        c.size
+    );
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = "\"".to_string();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    return out;
+}
+
+// Quotes a field if needed (RFC 4180).
+fn csv_field(s: &str) -> String {
+    if !s.contains([',', '"', '\n', '\r']) {
+        return s.to_string();
+    }
+    return format!("\"{}\"", s.replace('"', "\"\""));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::export::{csv_field, json_string, Export, Section};
+    use crate::printer::MockPrinter;
+    use crate::stats::Count;
+
+    fn count(files: u64, size: u64) -> Count {
+        let mut c = Count::new();
+        c.add(files, size);
+        return c;
+    }
+
+    #[test]
+    fn escapes_strings() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+        assert_eq!(csv_field("a/b"), "a/b");
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    #[test]
+    fn writes_json_and_csv() {
+        let mut e = Export::new(vec![("added", count(2, 30))]);
+        let mut s = Section::new("dirs", "dir");
+        s.add_row("a".to_string(), vec![("added", count(1, 10))]);
+        e.add_section(s);
+
+        let mut printer = MockPrinter::new();
+        e.write_json(&mut printer);
+        assert_eq!(
+            printer.flush(),
+            r#"{
+  "summary": {
+    "added": {"files": 2, "bytes": 30}
+  },
+  "dirs": [
+    {"dir": "a", "added": {"files": 1, "bytes": 10}}
+  ]
+}
+"#
+        );
+
+        e.write_csv(&mut printer);
+        assert_eq!(
+            printer.flush(),
+            "section,key,category,files,bytes\nsummary,,added,2,30\ndirs,a,added,1,10\n"
+        );
+    }
+}
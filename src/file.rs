@@ -1,34 +1,174 @@
 use crate::checksum::{CheckSum, CheckSummer};
+use crate::meta::Meta;
+use crate::similarity::Sketch;
+use crate::xattr::Xattrs;
 use std::path;
 
 pub type SizeBytes = u64;
 
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy)]
+pub enum Kind {
+    Regular,
+    Symlink,
+    Fifo,
+    Socket,
+    BlockDevice,
+    CharDevice,
+}
+
+impl Kind {
+    // Special files have no contents; they are only compared by their
+    // kind (and device number).
+    pub fn is_special(&self) -> bool {
+        return !matches!(self, Kind::Regular | Kind::Symlink);
+    }
+
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Kind::Regular => "file",
+            Kind::Symlink => "symlink",
+            Kind::Fifo => "fifo",
+            Kind::Socket => "socket",
+            Kind::BlockDevice => "block-device",
+            Kind::CharDevice => "char-device",
+        };
+    }
+}
+
+pub const DIR_NAME: &str = "dir";
+
 #[derive(Debug, Eq, Hash, PartialEq, Clone)]
 pub struct File {
     pub path: path::PathBuf,
+    pub kind: Kind,
     pub size: SizeBytes,
     pub check_sum: CheckSum,
+    // Only computed if similar files shall be detected.
+    pub sketch: Option<Sketch>,
+    // The checksum of the leading bytes of the file, only computed when
+    // requested by the snapshot (see `Snapshot::prefix_length`).
+    pub prefix_check_sum: Option<CheckSum>,
+    // For files that have shrunk, the checksum of the leading bytes of the
+    // file in snapshot 1, up to the size of this file (see
+    // `Snapshot::truncation_source`).
+    pub source_prefix_check_sum: Option<CheckSum>,
+    // Only computed if modified files shall be compared block by block.
+    pub block_check_sums: Option<Vec<CheckSum>>,
+    // Only known for files that were captured from disk.
+    pub meta: Option<Meta>,
+    // Only captured if extended attributes shall be compared.
+    pub xattrs: Option<Xattrs>,
+    // Only detected if the summary shall be broken down by file type.
+    pub file_type: Option<&'static str>,
+}
+
+// A directory, which is only tracked for the directory statistics.
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+pub struct Dir {
+    pub path: path::PathBuf,
+    // Whether the directory has no entries (that were recorded).
+    pub is_empty: bool,
+}
+
+impl Dir {
+    pub fn new(path: path::PathBuf, is_empty: bool) -> Dir {
+        return Dir { path, is_empty };
+    }
 }
 
 impl File {
-    pub fn new(path: path::PathBuf, size_bytes: SizeBytes, check_sum: CheckSum) -> File {
+    pub fn new(
+        path: path::PathBuf,
+        kind: Kind,
+        size_bytes: SizeBytes,
+        check_sum: CheckSum,
+    ) -> File {
         return File {
             path,
+            kind,
             size: size_bytes,
             check_sum,
+            sketch: None,
+            prefix_check_sum: None,
+            source_prefix_check_sum: None,
+            block_check_sums: None,
+            meta: None,
+            xattrs: None,
+            file_type: None,
         };
     }
 
+    pub fn with_sketch(mut self, sketch: Sketch) -> File {
+        self.sketch = Some(sketch);
+        return self;
+    }
+
+    pub fn with_meta(mut self, meta: Meta) -> File {
+        self.meta = Some(meta);
+        return self;
+    }
+
+    pub fn with_xattrs(mut self, xattrs: Xattrs) -> File {
+        self.xattrs = Some(xattrs);
+        return self;
+    }
+
+    pub fn with_file_type(mut self, file_type: &'static str) -> File {
+        self.file_type = Some(file_type);
+        return self;
+    }
+
+    pub fn with_prefix_check_sum(mut self, check_sum: CheckSum) -> File {
+        self.prefix_check_sum = Some(check_sum);
+        return self;
+    }
+
+    pub fn with_source_prefix_check_sum(mut self, check_sum: CheckSum) -> File {
+        self.source_prefix_check_sum = Some(check_sum);
+        return self;
+    }
+
+    pub fn with_block_check_sums(mut self, check_sums: Vec<CheckSum>) -> File {
+        self.block_check_sums = Some(check_sums);
+        return self;
+    }
+
     pub fn equals(&self, f2: &File) -> bool {
-        return self.check_sum == f2.check_sum && self.size == f2.size;
+        return self.kind == f2.kind && self.check_sum == f2.check_sum && self.size == f2.size;
     }
 
     #[allow(dead_code)]
     pub fn from_strings(path: &str, contents: &str) -> File {
         return File {
             path: path::Path::new(path).to_path_buf(),
+            kind: Kind::Regular,
             size: contents.len() as SizeBytes,
             check_sum: CheckSummer::new().consume(contents.as_bytes()).finalize(),
+            sketch: None,
+            prefix_check_sum: None,
+            source_prefix_check_sum: None,
+            block_check_sums: None,
+            meta: None,
+            xattrs: None,
+            file_type: None,
+        };
+    }
+
+    // A symlink whose “contents” is the text of its target.
+    #[allow(dead_code)]
+    pub fn symlink_from_strings(path: &str, target: &str) -> File {
+        return File {
+            path: path::Path::new(path).to_path_buf(),
+            kind: Kind::Symlink,
+            size: 0,
+            check_sum: CheckSummer::new().consume(target.as_bytes()).finalize(),
+            sketch: None,
+            prefix_check_sum: None,
+            source_prefix_check_sum: None,
+            block_check_sums: None,
+            meta: None,
+            xattrs: None,
+            file_type: None,
         };
     }
 }
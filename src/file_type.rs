@@ -0,0 +1,57 @@
+// Detects the type of a file from its leading bytes (“magic bytes”).
+
+// Signatures, as offset and bytes. The first match wins.
+const SIGNATURES: [(&str, usize, &[u8]); 29] = [
+    ("png", 0, b"\x89PNG\r\n\x1a\n"),
+    ("jpeg", 0, b"\xff\xd8\xff"),
+    ("gif", 0, b"GIF8"),
+    ("tiff", 0, b"II*\x00"),
+    ("tiff", 0, b"MM\x00*"),
+    ("bmp", 0, b"BM"),
+    ("webp", 8, b"WEBP"),
+    ("wav", 8, b"WAVE"),
+    ("avi", 8, b"AVI "),
+    ("mp4", 4, b"ftyp"),
+    ("matroska", 0, b"\x1a\x45\xdf\xa3"),
+    ("mp3", 0, b"ID3"),
+    ("ogg", 0, b"OggS"),
+    ("flac", 0, b"fLaC"),
+    ("pdf", 0, b"%PDF-"),
+    ("zip", 0, b"PK\x03\x04"),
+    ("gzip", 0, b"\x1f\x8b"),
+    ("bzip2", 0, b"BZh"),
+    ("xz", 0, b"\xfd7zXZ\x00"),
+    ("zstd", 0, b"\x28\xb5\x2f\xfd"),
+    ("7z", 0, b"7z\xbc\xaf\x27\x1c"),
+    ("rar", 0, b"Rar!\x1a\x07"),
+    ("tar", 257, b"ustar"),
+    ("sqlite", 0, b"SQLite format 3\x00"),
+    ("pgdump", 0, b"PGDMP"),
+    ("sql", 0, b"-- MySQL dump"),
+    ("sql", 0, b"-- PostgreSQL database dump"),
+    ("elf", 0, b"\x7fELF"),
+    ("macho", 0, b"\xcf\xfa\xed\xfe"),
+];
+
+// Returns the type for the leading bytes of a file, if it’s known.
+pub fn detect(head: &[u8]) -> Option<&'static str> {
+    return SIGNATURES
+        .iter()
+        .find(|(_, offset, magic)| head.get(*offset..*offset + magic.len()) == Some(*magic))
+        .map(|(name, _, _)| *name);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_type::detect;
+
+    #[test]
+    fn detects_types() {
+        assert_eq!(detect(b"\x89PNG\r\n\x1a\n...."), Some("png"));
+        assert_eq!(detect(b"\x00\x00\x00\x18ftypmp42"), Some("mp4"));
+        assert_eq!(detect(b"SQLite format 3\x00..."), Some("sqlite"));
+        assert_eq!(detect(b"%PDF"), None);
+        assert_eq!(detect(b"hello"), None);
+        assert_eq!(detect(b""), None);
+    }
+}
@@ -1,29 +1,45 @@
+use clap::ValueEnum;
 use std::ffi::OsString;
 use std::path;
 
 #[derive(Copy, Clone)]
 pub struct Filter {
-    include_symlinks: bool,
+    symlinks: SymlinkPolicy,
     include_dot_paths: bool,
 }
 
 const DOT_PREFIX: &str = ".";
 
+// Determines how symlinks are treated during the scan.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum SymlinkPolicy {
+    /// Ignore symlinks altogether.
+    Skip,
+    /// Count symlinks as files, and compare their target paths.
+    Record,
+    /// Traverse symlinks, and compare the files they point to.
+    Follow,
+}
+
 pub enum MatchReason {
     IsSymlink,
     IsDotPath,
 }
 
 impl Filter {
-    pub fn new(include_symlinks: bool, include_dot_paths: bool) -> Filter {
+    pub fn new(symlinks: SymlinkPolicy, include_dot_paths: bool) -> Filter {
         return Filter {
-            include_symlinks,
+            symlinks,
             include_dot_paths,
         };
     }
 
+    pub fn symlinks(&self) -> SymlinkPolicy {
+        return self.symlinks;
+    }
+
     pub fn matches(&self, p: &path::Path, name: &OsString) -> Option<MatchReason> {
-        if !self.include_symlinks && p.is_symlink() {
+        if self.symlinks == SymlinkPolicy::Skip && p.is_symlink() {
             return Some(MatchReason::IsSymlink);
         }
         if !self.include_dot_paths
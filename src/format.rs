@@ -4,13 +4,13 @@ const DECIMAL_SEPARATOR: &str = ",";
 const THOUSANDS_SEPARATOR: &str = ".";
 
 pub fn dec(x: i128) -> String {
-    fn d(s: String, i: i128) -> String {
+    fn d(i: i128) -> String {
         if i < 1000 {
             return format!("{i}");
         }
-        return format!("{}{}{:03}", d(s, i / 1000), DECIMAL_SEPARATOR, i % 1000);
+        return format!("{}{}{:03}", d(i / 1000), DECIMAL_SEPARATOR, i % 1000);
     }
-    let res = d("".to_string(), x.abs());
+    let res = d(x.abs());
     if x < 0 {
         return format!("-{res}");
     }
@@ -26,12 +26,12 @@ pub fn size_human(x: SizeBytes) -> String {
     let mut mantissa = x;
     let mut decimal = 0;
     let mut suffix = " B";
-    for s in vec![" K", " M", " G", " T", " P"] {
+    for s in [" K", " M", " G", " T", " P"] {
         if mantissa < 1000 {
             break;
         }
         decimal = (mantissa / 100) % 10;
-        mantissa = mantissa / 1000;
+        mantissa /= 1000;
         suffix = s;
     }
     let mut decimal_suffix = "".to_string();
@@ -63,6 +63,57 @@ pub fn percent(x: u64, base: u64) -> String {
     return format!("{} %", 100 * x / base);
 }
 
+// Parses a number of bytes, with an optional decimal (`K`, `M`, `G`,
+// `T`, `P`) or binary (`Ki`, `Mi`, …) unit suffix, e.g. `10G` or `4KiB`.
+pub fn parse_size(s: &str) -> Option<SizeBytes> {
+    let s = s.trim();
+    let s = s.strip_suffix('B').unwrap_or(s);
+    let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(split);
+    let number: SizeBytes = number.parse().ok()?;
+    let (prefix, base) = match unit.strip_suffix('i') {
+        Some(prefix) => (prefix, 1024),
+        None => (unit, 1000),
+    };
+    let exponent = match prefix {
+        "" if base == 1000 => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        "P" => 5,
+        _ => return None,
+    };
+    return number.checked_mul(SizeBytes::checked_pow(base, exponent)?);
+}
+
+// Formats seconds since the Unix epoch as UTC date and time (ISO 8601).
+pub fn timestamp(secs: i64) -> String {
+    let days = secs.div_euclid(86_400);
+    let time = secs.rem_euclid(86_400);
+
+    // Converts days to a civil date, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    return format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        time / 3_600,
+        time / 60 % 60,
+        time % 60
+    );
+}
+
 #[cfg(test)]
 mod tests {
     use crate::format;
@@ -115,8 +166,8 @@ mod tests {
         assert_eq!(format::duration_human(34 * 60 + 12), "34m 12s");
         assert_eq!(format::duration_human(59 * 60 + 59), "59m 59s");
         assert_eq!(format::duration_human(60 * 60), "1h 0m 0s");
-        assert_eq!(format::duration_human(1 * 60 * 60 + 1), "1h 0m 1s");
-        assert_eq!(format::duration_human(1 * 60 * 60 + 44 * 60), "1h 44m 0s");
+        assert_eq!(format::duration_human(60 * 60 + 1), "1h 0m 1s");
+        assert_eq!(format::duration_human(60 * 60 + 44 * 60), "1h 44m 0s");
         assert_eq!(
             format::duration_human(7 * 60 * 60 + 32 * 60 + 54),
             "7h 32m 54s"
@@ -134,4 +185,29 @@ mod tests {
         assert_eq!(format::percent(50, 101), "49 %");
         assert_eq!(format::percent(75, 50), "150 %");
     }
+
+    #[test]
+    fn parse_size() {
+        assert_eq!(format::parse_size("0"), Some(0));
+        assert_eq!(format::parse_size("123"), Some(123));
+        assert_eq!(format::parse_size("123B"), Some(123));
+        assert_eq!(format::parse_size("4K"), Some(4_000));
+        assert_eq!(format::parse_size("4KiB"), Some(4_096));
+        assert_eq!(format::parse_size("10G"), Some(10_000_000_000));
+        assert_eq!(format::parse_size("1Ti"), Some(1_099_511_627_776));
+        assert_eq!(format::parse_size(""), None);
+        assert_eq!(format::parse_size("G"), None);
+        assert_eq!(format::parse_size("1.5G"), None);
+        assert_eq!(format::parse_size("4i"), None);
+        assert_eq!(format::parse_size("4X"), None);
+        assert_eq!(format::parse_size("99999999P"), None);
+    }
+
+    #[test]
+    fn timestamp() {
+        assert_eq!(format::timestamp(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format::timestamp(951_782_400), "2000-02-29T00:00:00Z");
+        assert_eq!(format::timestamp(1_700_000_000), "2023-11-14T22:13:20Z");
+        assert_eq!(format::timestamp(-1), "1969-12-31T23:59:59Z");
+    }
 }
@@ -0,0 +1,81 @@
+use globset::GlobBuilder;
+use serde::Deserialize;
+use std::path;
+
+// A pattern for relative paths, where `*` matches any characters within a
+// path component, `**` matches any number of path components and `?`
+// matches a single character (except `/`). A trailing `/` matches
+// everything within a directory, i.e. `logs/` is the same as `logs/**`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Glob {
+    matcher: globset::GlobMatcher,
+    text: String,
+}
+
+impl Glob {
+    pub fn new(pattern: &str) -> Result<Glob, String> {
+        let mut p = pattern.trim_start_matches("./").to_string();
+        if p.ends_with('/') {
+            p.push_str("**");
+        }
+        let glob = GlobBuilder::new(&p)
+            .literal_separator(true)
+            .build()
+            .map_err(|e| e.to_string())?;
+        return Ok(Glob {
+            matcher: glob.compile_matcher(),
+            text: pattern.to_string(),
+        });
+    }
+
+    pub fn matches(&self, p: &path::Path) -> bool {
+        return self.matcher.is_match(p);
+    }
+
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+}
+
+impl TryFrom<String> for Glob {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Glob, String> {
+        return Glob::new(&pattern);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::glob::Glob;
+    use std::path::Path;
+
+    fn matches(pattern: &str, p: &str) -> bool {
+        return Glob::new(pattern).unwrap().matches(Path::new(p));
+    }
+
+    #[test]
+    fn matches_paths() {
+        assert!(matches("a.txt", "a.txt"));
+        assert!(!matches("a.txt", "b/a.txt"));
+        assert!(matches("*.txt", "a.txt"));
+        assert!(!matches("*.txt", "b/a.txt"));
+        assert!(matches("**/*.txt", "a.txt"));
+        assert!(matches("**/*.txt", "b/c/a.txt"));
+        assert!(matches("photos/**", "photos/2023/a.jpg"));
+        assert!(matches("photos/", "photos/a.jpg"));
+        assert!(!matches("photos/", "photos2/a.jpg"));
+        assert!(matches("logs/*/?.log", "logs/x/1.log"));
+        assert!(!matches("logs/*/?.log", "logs/x/12.log"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(matches("./a", "a"));
+        assert!(matches("*.{jpg,png}", "a.png"));
+    }
+
+    #[test]
+    fn rejects_invalid_patterns() {
+        assert!(Glob::new("a/[b").is_err());
+    }
+}
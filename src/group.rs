@@ -0,0 +1,186 @@
+use crate::file::{File, Kind, SizeBytes};
+use crate::report::Report;
+use crate::stats::Count;
+use std::collections::HashMap;
+use std::path;
+
+// The categories that the summary is broken down by, in the order of
+// the summary table. Moved+modified, relocated, type-changed and expected
+// files are not broken down.
+pub const CATEGORIES: [&str; 6] = [
+    "identical",
+    "moved",
+    "copied",
+    "added",
+    "deleted",
+    "modified",
+];
+const IDENTICAL: usize = 0;
+const MOVED: usize = 1;
+const COPIED: usize = 2;
+const ADDED: usize = 3;
+const DELETED: usize = 4;
+const MODIFIED: usize = 5;
+
+// The files (and bytes) per category, for a subset of all files, e.g.
+// for the files of one directory.
+#[derive(Debug)]
+pub struct Group {
+    pub key: String,
+    pub counts: [Count; CATEGORIES.len()],
+}
+
+impl Group {
+    fn new(key: String) -> Group {
+        return Group {
+            key,
+            counts: [Count::new(); CATEGORIES.len()],
+        };
+    }
+
+    // The bytes that were added, deleted or modified.
+    pub fn changed_bytes(&self) -> SizeBytes {
+        return [ADDED, DELETED, MODIFIED]
+            .iter()
+            .map(|i| self.counts[*i].size)
+            .sum();
+    }
+}
+
+// Breaks down the summary categories by the given key. The groups are
+// sorted by their changed bytes, in descending order. For modified and
+// moved files, the key is taken from the file as in snapshot 2.
+pub fn group_by<F>(report: &Report, key_of: F) -> Vec<Group>
+where
+    F: Fn(&File) -> String,
+{
+    let mut groups: HashMap<String, Group> = HashMap::new();
+    for_each_file(report, |category, f| {
+        let key = key_of(f);
+        groups
+            .entry(key.clone())
+            .or_insert_with(|| Group::new(key))
+            .counts[category]
+            .add(1, f.size);
+    });
+    let mut groups: Vec<Group> = groups.into_values().collect();
+    groups.sort_by(|g1, g2| {
+        return g2
+            .changed_bytes()
+            .cmp(&g1.changed_bytes())
+            .then_with(|| g1.key.cmp(&g2.key));
+    });
+    return groups;
+}
+
+// The directory of a file, cut off after `depth` components. Files in
+// the root directory belong to `.`.
+pub fn dir_key(p: &path::Path, depth: usize) -> String {
+    let dir: path::PathBuf = p
+        .parent()
+        .map(|d| d.components().take(depth).collect())
+        .unwrap_or_default();
+    if dir.as_os_str().is_empty() {
+        return ".".to_string();
+    }
+    return dir.display().to_string();
+}
+
+// The (lower-case) extension of a file, or `(none)`.
+pub fn ext_key(p: &path::Path) -> String {
+    return p
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or("(none)".to_string());
+}
+
+// The detected type of a file, or `unknown`. Entries other than regular
+// files are keyed by their kind.
+pub fn type_key(f: &File) -> String {
+    if f.kind != Kind::Regular {
+        return f.kind.name().to_string();
+    }
+    return f.file_type.unwrap_or("unknown").to_string();
+}
+
+// Visits all files that the summary categories are made up of. Identical
+// files are only visited if the report has stored them.
+pub fn for_each_file<F>(report: &Report, mut visit: F)
+where
+    F: FnMut(usize, &File),
+{
+    for f in report.identical.files().into_iter().flatten() {
+        visit(IDENTICAL, f);
+    }
+    for d in &report.dir_moves {
+        for f in &d.files {
+            visit(MOVED, f);
+        }
+    }
+    for (_, f2) in report.moved.pairs().iter().chain(report.respelled.pairs()) {
+        visit(MOVED, f2);
+    }
+    for f in report.copied.files().into_iter().flatten() {
+        visit(COPIED, f);
+    }
+    for f in report.added.files().into_iter().flatten() {
+        visit(ADDED, f);
+    }
+    for f in report.deleted.files().into_iter().flatten() {
+        visit(DELETED, f);
+    }
+    for f in report.modified_snap_2.files().into_iter().flatten() {
+        visit(MODIFIED, f);
+    }
+    for (_, f2) in report
+        .appended
+        .pairs()
+        .iter()
+        .chain(report.truncated.pairs())
+    {
+        visit(MODIFIED, f2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::group::{dir_key, ext_key, group_by, type_key};
+    use crate::report::Report;
+    use std::path::Path;
+
+    #[test]
+    fn cuts_off_dirs() {
+        assert_eq!(dir_key(Path::new("a.txt"), 1), ".");
+        assert_eq!(dir_key(Path::new("a/b/c/d.txt"), 1), "a");
+        assert_eq!(dir_key(Path::new("a/b/c/d.txt"), 2), "a/b");
+        assert_eq!(dir_key(Path::new("a/b.txt"), 2), "a");
+    }
+
+    #[test]
+    fn determines_keys() {
+        assert_eq!(ext_key(Path::new("a/b.JPG")), "jpg");
+        assert_eq!(ext_key(Path::new("a.tar.gz")), "gz");
+        assert_eq!(ext_key(Path::new("a/Makefile")), "(none)");
+        let f = File::from_strings("a.txt", "a");
+        assert_eq!(type_key(&f), "unknown");
+        assert_eq!(type_key(&f.with_file_type("pdf")), "pdf");
+        assert_eq!(type_key(&File::symlink_from_strings("l", "a")), "symlink");
+    }
+
+    #[test]
+    fn groups_files() {
+        let mut r = Report::new();
+        r.added.record(&File::from_strings("a/1.txt", "1"));
+        r.added.record(&File::from_strings("b/2.txt", "22"));
+        r.deleted.record(&File::from_strings("b/3.txt", "333"));
+        r.modified_snap_2.record(&File::from_strings("4.txt", "4"));
+
+        let groups = group_by(&r, |f| dir_key(&f.path, 1));
+        let keys: Vec<&str> = groups.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["b", ".", "a"]);
+        assert_eq!(groups[0].changed_bytes(), 5);
+        assert_eq!(groups[0].counts[3].files, 1);
+        assert_eq!(groups[0].counts[4].files, 1);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::file::SizeBytes;
+use crate::group::{for_each_file, CATEGORIES};
+use crate::report::Report;
+use crate::stats::Count;
+
+const K: SizeBytes = 1024;
+const DAY: i64 = 24 * 60 * 60;
+
+// Log-scale size buckets, as label and (exclusive) upper bound.
+pub const SIZE_BUCKETS: [(&str, SizeBytes); 7] = [
+    ("<4K", 4 * K),
+    ("<64K", 64 * K),
+    ("<1M", K * K),
+    ("<16M", 16 * K * K),
+    ("<256M", 256 * K * K),
+    ("<1G", K * K * K),
+    (">=1G", SizeBytes::MAX),
+];
+
+// Buckets for the age of the files (by modification time), as label and
+// (exclusive) upper bound in seconds.
+pub const AGE_BUCKETS: [(&str, i64); 6] = [
+    ("<1d", DAY),
+    ("<1w", 7 * DAY),
+    ("<1mo", 30 * DAY),
+    ("<1y", 365 * DAY),
+    ("<5y", 5 * 365 * DAY),
+    (">=5y", i64::MAX),
+];
+
+// The distribution of file sizes and file ages, per summary category.
+#[derive(Debug)]
+pub struct Histograms {
+    pub size: [[Count; SIZE_BUCKETS.len()]; CATEGORIES.len()],
+    pub age: [[Count; AGE_BUCKETS.len()]; CATEGORIES.len()],
+}
+
+impl Histograms {
+    // Sorts the files of the report into buckets. The age is relative to
+    // `now` (in seconds since the Unix epoch); files whose modification
+    // time is unknown are left out of the age histogram.
+    pub fn from(report: &Report, now: i64) -> Histograms {
+        let mut h = Histograms {
+            size: [[Count::new(); SIZE_BUCKETS.len()]; CATEGORIES.len()],
+            age: [[Count::new(); AGE_BUCKETS.len()]; CATEGORIES.len()],
+        };
+        for_each_file(report, |category, f| {
+            let s = SIZE_BUCKETS.iter().position(|(_, max)| f.size < *max);
+            h.size[category][s.unwrap_or(SIZE_BUCKETS.len() - 1)].add(1, f.size);
+            if let Some(m) = f.meta {
+                let age = (now - m.mtime).max(0);
+                let a = AGE_BUCKETS.iter().position(|(_, max)| age < *max);
+                h.age[category][a.unwrap_or(AGE_BUCKETS.len() - 1)].add(1, f.size);
+            }
+        });
+        return h;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::histogram::Histograms;
+    use crate::meta::Meta;
+    use crate::report::Report;
+
+    #[test]
+    fn sorts_into_buckets() {
+        let meta = |mtime| Meta {
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            mtime,
+            mtime_nsec: 0,
+        };
+        let mut r = Report::new();
+        r.added
+            .record(&File::from_strings("a", "").with_meta(meta(1_000)));
+        r.added
+            .record(&File::from_strings("b", &"b".repeat(5_000)).with_meta(meta(0)));
+        r.deleted.record(&File::from_strings("c", "c"));
+
+        let h = Histograms::from(&r, 10 * 24 * 60 * 60);
+        assert_eq!(h.size[3][0].files, 1);
+        assert_eq!(h.size[3][1].files, 1);
+        assert_eq!(h.size[3][1].size, 5_000);
+        assert_eq!(h.size[4][0].files, 1);
+        assert_eq!(h.age[3][2].files, 2);
+        assert_eq!(h.age[4].iter().map(|c| c.files).sum::<u64>(), 0);
+    }
+}
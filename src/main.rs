@@ -1,63 +1,203 @@
+#![allow(clippy::needless_return)]
+
+mod assertion;
+mod baseline;
 mod checksum;
 mod cli;
 mod dir_iter;
+mod dir_move;
 mod error;
+mod export;
 mod file;
+mod file_type;
 mod filter;
 mod format;
+mod glob;
+mod group;
+mod histogram;
+mod meta;
+mod pairing;
+mod path_key;
+mod policy;
 mod printer;
 mod progress;
+mod relocation;
 mod report;
+mod scope;
+mod similarity;
 mod snapper;
 mod snapshot;
 mod snapshot_1;
 mod snapshot_2;
 mod stats;
+mod table;
+mod timeline;
+mod top;
+mod xattr;
 
+use crate::assertion::Assertion;
 use crate::cli::Cli;
 use crate::dir_iter::DirIterator;
 use crate::error::Error;
-use crate::printer::{SNP1, SNP2};
+use crate::printer::{Printer, SNP1, SNP2};
 use crate::progress::Progress;
 use crate::snapper::Snapper;
 use crate::snapshot_1::Snapshot1;
 use crate::snapshot_2::Snapshot2;
+use crate::timeline::Tee;
 use std::process;
 
-fn run() -> Result<(), Error> {
+// Exit codes, similar to `diff`. Files that might be corrupted (see
+// “Suspicious”), failed assertions (see `--fail-if`) and policy violations
+// take precedence over the regular result.
+const EXIT_IDENTICAL: i32 = 0;
+const EXIT_DIFFERENT: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+const EXIT_SUSPICIOUS: i32 = 3;
+const EXIT_ASSERTION_FAILED: i32 = 4;
+const EXIT_POLICY_VIOLATED: i32 = 5;
+
+// Returns the exit code.
+fn run() -> Result<i32, Error> {
     let cli = Cli::new_from_env()?;
+    if cli.is_timeline {
+        return run_timeline(cli);
+    }
 
     // Process snapshot 1.
     let snap1 = {
         let mut progress1 = Progress::new(cli.terminal_printer, SNP1, None);
-        let dir_it1 =
-            DirIterator::scan(cli.workers1, &cli.snap1_root, cli.filters, &mut progress1)?;
-        let snapper1 = Snapper::new(cli.workers1, cli.ctrl_c.clone());
-        let snap1 = Snapshot1::new();
+        let dir_it1 = DirIterator::scan(
+            cli.workers1,
+            &cli.snap_roots[0],
+            &cli.scope,
+            cli.filters,
+            &mut progress1,
+        )?;
+        let snapper1 = Snapper::new(cli.workers1, cli.ctrl_c.clone(), cli.capture);
+        let snap1 = Snapshot1::new_with_keys(cli.path_keys.clone());
         snapper1.process(dir_it1, snap1, progress1)?
     };
 
     // Process snapshot 2.
     let report = {
         let mut progress2 = Progress::new(cli.terminal_printer, SNP2, Some(snap1.total().count));
-        let dir_it2 =
-            DirIterator::scan(cli.workers2, &cli.snap2_root, cli.filters, &mut progress2)?;
-        let snapper2 = Snapper::new(cli.workers2, cli.ctrl_c.clone());
-        let snap2 = Snapshot2::new(snap1);
+        let dir_it2 = DirIterator::scan(
+            cli.workers2,
+            &cli.snap_roots[1],
+            &cli.scope,
+            cli.filters,
+            &mut progress2,
+        )?;
+        let snapper2 = Snapper::new(cli.workers2, cli.ctrl_c.clone(), cli.capture);
+        let snap2 = Snapshot2::new_with_options(snap1, cli.snapshot_options.clone());
         snapper2.process(dir_it2, snap2, progress2)?.conclude()
     };
 
     // Print report.
-    report.summary(cli.terminal_printer);
-    cli.file_printer
-        .map(|mut printer| report.detailed_list(&mut printer));
-    return Ok(());
+    let mut terminal_printer = cli.terminal_printer;
+    report.summary(terminal_printer);
+    let counts = report.summary_counts();
+    let failed: Vec<&Assertion> = cli
+        .assertions
+        .iter()
+        .filter(|a| a.is_met(&counts))
+        .collect();
+    if !failed.is_empty() {
+        assertion::print_failed(&mut terminal_printer, &failed, &counts);
+    }
+    if let Some(mut printer) = cli.file_printer {
+        report.detailed_list(&mut printer);
+    }
+    if let Some(mut printer) = cli.json_printer {
+        report.export().write_json(&mut printer);
+    }
+    if let Some(mut printer) = cli.csv_printer {
+        report.export().write_csv(&mut printer);
+    }
+    if report.suspicious.count.files > 0 {
+        return Ok(EXIT_SUSPICIOUS);
+    }
+    if !failed.is_empty() {
+        return Ok(EXIT_ASSERTION_FAILED);
+    }
+    if report.policy_violations.iter().any(|vs| !vs.is_empty()) {
+        return Ok(EXIT_POLICY_VIOLATED);
+    }
+    if report.has_differences() {
+        return Ok(EXIT_DIFFERENT);
+    }
+    return Ok(EXIT_IDENTICAL);
+}
+
+// Compares each snapshot with the previous one. Each snapshot is read only
+// once: while it’s compared with the previous snapshot, it’s recorded for
+// the comparison with the next one.
+fn run_timeline(cli: Cli) -> Result<i32, Error> {
+    let roots = &cli.snap_roots;
+    let mut prev = {
+        let mut progress = Progress::new(cli.terminal_printer, SNP1, None);
+        let dir_it = DirIterator::scan(
+            cli.workers1,
+            &roots[0],
+            &cli.scope,
+            cli.filters,
+            &mut progress,
+        )?;
+        let snapper = Snapper::new(cli.workers1, cli.ctrl_c.clone(), cli.capture);
+        snapper.process(
+            dir_it,
+            Snapshot1::new_with_keys(cli.path_keys.clone()),
+            progress,
+        )?
+    };
+    let mut intervals = vec![];
+    for (i, pair) in roots.windows(2).enumerate() {
+        let (root_1, root_2) = (&pair[0], &pair[1]);
+        let label = format!("Snap {}", i + 2);
+        let mut progress = Progress::new(cli.terminal_printer, &label, Some(prev.total().count));
+        let dir_it =
+            DirIterator::scan(cli.workers2, root_2, &cli.scope, cli.filters, &mut progress)?;
+        let mut options = cli.snapshot_options.clone();
+        options.snap_1_root = Some(root_1.clone());
+        let tee = Tee::new(
+            Snapshot2::new_with_options(prev, options),
+            Snapshot1::new_with_keys(cli.path_keys.clone()),
+        );
+        let snapper = Snapper::new(cli.workers2, cli.ctrl_c.clone(), cli.capture);
+        let (report, next) = snapper.process(dir_it, tee, progress)?.conclude();
+        let name = format!(
+            "{} → {}",
+            timeline::snap_name(root_1),
+            timeline::snap_name(root_2)
+        );
+        intervals.push((name, report));
+        prev = next;
+    }
+
+    let mut terminal_printer = cli.terminal_printer;
+    timeline::print_timeline(&mut terminal_printer, &intervals);
+    if let Some(mut printer) = cli.file_printer {
+        for (name, report) in &intervals {
+            printer.print(format!("#int {}\n", name));
+            report.detailed_list(&mut printer);
+        }
+    }
+    if intervals.iter().any(|(_, r)| r.suspicious.count.files > 0) {
+        return Ok(EXIT_SUSPICIOUS);
+    }
+    if intervals.iter().any(|(_, r)| r.has_differences()) {
+        return Ok(EXIT_DIFFERENT);
+    }
+    return Ok(EXIT_IDENTICAL);
 }
 
 fn main() {
-    let status = run();
-    if !status.is_ok() {
-        println!("{}", status.unwrap_err());
-        process::exit(1);
+    match run() {
+        Ok(code) => process::exit(code),
+        Err(e) => {
+            println!("{}", e);
+            process::exit(EXIT_ERROR);
+        }
     }
 }
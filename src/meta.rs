@@ -0,0 +1,204 @@
+use crate::file::File;
+use crate::format::timestamp;
+use crate::stats::{self, Count};
+use std::fs;
+use std::os::unix::fs::MetadataExt;
+
+// File system metadata of a file, as captured during scanning.
+#[derive(Debug, Eq, Hash, PartialEq, Clone, Copy)]
+pub struct Meta {
+    // Permission bits (including setuid, setgid and sticky bit).
+    pub mode: u32,
+    pub uid: u32,
+    pub gid: u32,
+    // Seconds since the Unix epoch.
+    pub mtime: i64,
+    // The sub-second part of the modification time, in nanoseconds.
+    pub mtime_nsec: i64,
+}
+
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum Attribute {
+    Mode,
+    Uid,
+    Gid,
+    Mtime,
+}
+
+impl Meta {
+    pub fn from(m: &fs::Metadata) -> Meta {
+        return Meta {
+            mode: m.mode() & 0o7777,
+            uid: m.uid(),
+            gid: m.gid(),
+            mtime: m.mtime(),
+            mtime_nsec: m.mtime_nsec(),
+        };
+    }
+
+    // The attributes that differ between both.
+    pub fn diff(&self, other: &Meta) -> Vec<Attribute> {
+        let mut attrs = vec![];
+        if self.mode != other.mode {
+            attrs.push(Attribute::Mode);
+        }
+        if self.uid != other.uid {
+            attrs.push(Attribute::Uid);
+        }
+        if self.gid != other.gid {
+            attrs.push(Attribute::Gid);
+        }
+        if self.mtime != other.mtime {
+            attrs.push(Attribute::Mtime);
+        }
+        return attrs;
+    }
+
+    // Formats an attribute for the detailed report.
+    pub fn show(&self, a: Attribute) -> String {
+        return match a {
+            Attribute::Mode => format!("{:04o}", self.mode),
+            Attribute::Uid => self.uid.to_string(),
+            Attribute::Gid => self.gid.to_string(),
+            Attribute::Mtime => timestamp(self.mtime),
+        };
+    }
+}
+
+impl Attribute {
+    pub fn name(&self) -> &'static str {
+        return match self {
+            Attribute::Mode => "mode",
+            Attribute::Uid => "uid",
+            Attribute::Gid => "gid",
+            Attribute::Mtime => "mtime",
+        };
+    }
+}
+
+// Files whose contents is identical, but whose metadata has changed.
+// The count is based on the file from snapshot 2.
+#[derive(Debug)]
+pub struct MetaChanges {
+    pub count: Count,
+    pub mode: u64,
+    pub uid: u64,
+    pub gid: u64,
+    pub mtime: u64,
+    pairs: Vec<(File, File)>,
+}
+
+impl MetaChanges {
+    pub fn new() -> MetaChanges {
+        return MetaChanges {
+            count: Count::new(),
+            mode: 0,
+            uid: 0,
+            gid: 0,
+            mtime: 0,
+            pairs: vec![],
+        };
+    }
+
+    // Records the pair, if the metadata of both files is known and differs.
+    pub fn record(&mut self, f1: &File, f2: &File) {
+        let attrs = match (&f1.meta, &f2.meta) {
+            (Some(m1), Some(m2)) => m1.diff(m2),
+            _ => return,
+        };
+        if attrs.is_empty() {
+            return;
+        }
+        for a in attrs {
+            match a {
+                Attribute::Mode => self.mode += 1,
+                Attribute::Uid => self.uid += 1,
+                Attribute::Gid => self.gid += 1,
+                Attribute::Mtime => self.mtime += 1,
+            }
+        }
+        self.count.add(1, f2.size);
+        self.pairs.push((f1.clone(), f2.clone()));
+    }
+
+    pub fn pairs(&self) -> &Vec<(File, File)> {
+        return &self.pairs;
+    }
+
+    pub fn withdraw_if<F>(&mut self, is_removed: F) -> Vec<(File, File)>
+    where
+        F: FnMut(&(File, File)) -> bool,
+    {
+        let removed = stats::withdraw_if(&mut self.pairs, is_removed);
+        for (f1, f2) in &removed {
+            for a in f1.meta.unwrap().diff(&f2.meta.unwrap()) {
+                match a {
+                    Attribute::Mode => self.mode -= 1,
+                    Attribute::Uid => self.uid -= 1,
+                    Attribute::Gid => self.gid -= 1,
+                    Attribute::Mtime => self.mtime -= 1,
+                }
+            }
+            self.count.remove(1, f2.size);
+        }
+        return removed;
+    }
+
+    // Lists the changed attributes with their counts, e.g. `mode 3, uid 1`.
+    pub fn summary(&self) -> String {
+        return [
+            (Attribute::Mode, self.mode),
+            (Attribute::Uid, self.uid),
+            (Attribute::Gid, self.gid),
+            (Attribute::Mtime, self.mtime),
+        ]
+        .iter()
+        .filter(|(_, n)| *n > 0)
+        .map(|(a, n)| format!("{} {}", a.name(), n))
+        .collect::<Vec<String>>()
+        .join(", ");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::meta::{Meta, MetaChanges};
+
+    fn meta(mode: u32, uid: u32, mtime: i64) -> Meta {
+        return Meta {
+            mode,
+            uid,
+            gid: 100,
+            mtime,
+            mtime_nsec: 0,
+        };
+    }
+
+    #[test]
+    fn records_changes() {
+        let mut c = MetaChanges::new();
+        let f = File::from_strings("/a", "a");
+        c.record(&f, &f);
+        c.record(
+            &f.clone().with_meta(meta(0o644, 0, 0)),
+            &f.clone().with_meta(meta(0o644, 0, 0)),
+        );
+        assert_eq!(c.count.files, 0);
+
+        c.record(
+            &f.clone().with_meta(meta(0o644, 1000, 0)),
+            &f.clone().with_meta(meta(0o755, 0, 0)),
+        );
+        c.record(
+            &f.clone().with_meta(meta(0o644, 0, 0)),
+            &f.clone().with_meta(meta(0o755, 0, 1)),
+        );
+        assert_eq!(c.count.files, 2);
+        assert_eq!(c.summary(), "mode 2, uid 1, mtime 1");
+        let (f1, f2) = &c.pairs()[0];
+        let (m1, m2) = (f1.meta.unwrap(), f2.meta.unwrap());
+        assert_eq!(m1.show(crate::meta::Attribute::Mode), "0644");
+        assert_eq!(m2.show(crate::meta::Attribute::Mode), "0755");
+    }
+}
@@ -0,0 +1,212 @@
+use crate::file::File;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path;
+
+// Above this number of candidate combinations, pairs are not scored
+// exhaustively anymore, to avoid quadratic runtime.
+const MAX_SCORED_PAIRS: usize = 1_000_000;
+
+// Above this number of files with the same name, only the first ones
+// are considered when looking for the best match.
+const MAX_NAME_CANDIDATES: usize = 1_000;
+
+// Pairs up files from snapshot 1 with files from snapshot 2 (which are
+// assumed to have the same contents), so that the pairs resemble each
+// other as closely as possible. Files that share the same name are
+// preferred, and then files that share the longest common directory
+// prefix. Returns the pairs, and the files that couldn’t be paired up.
+pub fn pair_up(f1s: Vec<File>, f2s: Vec<File>) -> (Vec<(File, File)>, Vec<File>, Vec<File>) {
+    return pair_up_where(f1s, f2s, |_, _| true);
+}
+
+// Like `pair_up`, but only pairs up files for which `is_eligible` holds.
+pub fn pair_up_where<F>(
+    mut f1s: Vec<File>,
+    mut f2s: Vec<File>,
+    is_eligible: F,
+) -> (Vec<(File, File)>, Vec<File>, Vec<File>)
+where
+    F: Fn(&File, &File) -> bool,
+{
+    f1s.sort_by(|a, b| a.path.cmp(&b.path));
+    f2s.sort_by(|a, b| a.path.cmp(&b.path));
+    let assignment = if f1s.len() * f2s.len() <= MAX_SCORED_PAIRS {
+        assign_by_score(&f1s, &f2s, &is_eligible)
+    } else {
+        assign_by_name(&f1s, &f2s, &is_eligible)
+    };
+
+    let mut f1_slots: Vec<Option<File>> = f1s.into_iter().map(Some).collect();
+    let mut pairs = vec![];
+    let mut leftover_f2s = vec![];
+    for (j, f2) in f2s.into_iter().enumerate() {
+        match assignment[j] {
+            Some(i) => pairs.push((f1_slots[i].take().unwrap(), f2)),
+            None => leftover_f2s.push(f2),
+        }
+    }
+    let leftover_f1s = f1_slots.into_iter().flatten().collect();
+    return (pairs, leftover_f1s, leftover_f2s);
+}
+
+// Scores all combinations, and assigns the best-scoring pairs first.
+// Returns the index of the assigned `f1` for every `f2`.
+fn assign_by_score<F>(f1s: &[File], f2s: &[File], is_eligible: &F) -> Vec<Option<usize>>
+where
+    F: Fn(&File, &File) -> bool,
+{
+    let mut candidates = Vec::with_capacity(f1s.len() * f2s.len());
+    for (i, f1) in f1s.iter().enumerate() {
+        for (j, f2) in f2s.iter().enumerate() {
+            if !is_eligible(f1, f2) {
+                continue;
+            }
+            candidates.push((similarity(&f1.path, &f2.path), i, j));
+        }
+    }
+    candidates.sort_by_key(|(s, i, j)| (Reverse(*s), *i, *j));
+    let mut is_taken = vec![false; f1s.len()];
+    let mut assignment = vec![None; f2s.len()];
+    for (_, i, j) in candidates {
+        if is_taken[i] || assignment[j].is_some() {
+            continue;
+        }
+        is_taken[i] = true;
+        assignment[j] = Some(i);
+    }
+    return assignment;
+}
+
+// Assigns files with the same name (in a greedy fashion), and then the
+// remaining files in order.
+fn assign_by_name<F>(f1s: &[File], f2s: &[File], is_eligible: &F) -> Vec<Option<usize>>
+where
+    F: Fn(&File, &File) -> bool,
+{
+    let mut by_name: HashMap<Option<OsString>, Vec<usize>> = HashMap::new();
+    for (i, f1) in f1s.iter().enumerate() {
+        by_name
+            .entry(f1.path.file_name().map(|n| n.to_os_string()))
+            .or_default()
+            .push(i);
+    }
+    let mut is_taken = vec![false; f1s.len()];
+    let mut assignment = vec![None; f2s.len()];
+    for (j, f2) in f2s.iter().enumerate() {
+        let best = by_name
+            .get(&f2.path.file_name().map(|n| n.to_os_string()))
+            .and_then(|is| {
+                is.iter()
+                    .filter(|i| !is_taken[**i] && is_eligible(&f1s[**i], f2))
+                    .take(MAX_NAME_CANDIDATES)
+                    .max_by_key(|i| (similarity(&f1s[**i].path, &f2.path), Reverse(**i)))
+                    .copied()
+            });
+        if let Some(i) = best {
+            is_taken[i] = true;
+            assignment[j] = Some(i);
+        }
+    }
+    let mut first_free = 0;
+    for (j, f2) in f2s.iter().enumerate() {
+        if assignment[j].is_some() {
+            continue;
+        }
+        while first_free < f1s.len() && is_taken[first_free] {
+            first_free += 1;
+        }
+        let found = (first_free..f1s.len()).find(|i| !is_taken[*i] && is_eligible(&f1s[*i], f2));
+        if let Some(i) = found {
+            is_taken[i] = true;
+            assignment[j] = Some(i);
+        }
+    }
+    return assignment;
+}
+
+// Whether both paths have the same file name, and how many leading
+// directory components they have in common.
+fn similarity(p1: &path::Path, p2: &path::Path) -> (bool, usize) {
+    let same_name = p1.file_name() == p2.file_name();
+    let common_dirs = p1
+        .parent()
+        .unwrap_or(p1)
+        .components()
+        .zip(p2.parent().unwrap_or(p2).components())
+        .take_while(|(c1, c2)| c1 == c2)
+        .count();
+    return (same_name, common_dirs);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::pairing::{assign_by_name, pair_up};
+
+    fn paths(pairs: &[(File, File)]) -> Vec<(&str, &str)> {
+        return pairs
+            .iter()
+            .map(|(f1, f2)| (f1.path.to_str().unwrap(), f2.path.to_str().unwrap()))
+            .collect();
+    }
+
+    #[test]
+    fn pairs_by_name_and_dir() {
+        let (pairs, leftover_f1s, leftover_f2s) = pair_up(
+            vec![
+                File::from_strings("a/logo.png", "x"),
+                File::from_strings("z/other/logo.png", "x"),
+                File::from_strings("b/icon.png", "x"),
+            ],
+            vec![
+                File::from_strings("z/other/img/logo.png", "x"),
+                File::from_strings("a/img/logo.png", "x"),
+                File::from_strings("b/favicon.png", "x"),
+                File::from_strings("c/icon.png", "x"),
+            ],
+        );
+        assert_eq!(
+            paths(&pairs),
+            vec![
+                ("a/logo.png", "a/img/logo.png"),
+                ("b/icon.png", "c/icon.png"),
+                ("z/other/logo.png", "z/other/img/logo.png"),
+            ]
+        );
+        assert!(leftover_f1s.is_empty());
+        assert_eq!(leftover_f2s[0].path.to_str(), Some("b/favicon.png"));
+    }
+
+    #[test]
+    fn leaves_surplus_files() {
+        let (pairs, leftover_f1s, leftover_f2s) = pair_up(
+            vec![
+                File::from_strings("a/1.txt", "x"),
+                File::from_strings("b/2.txt", "x"),
+            ],
+            vec![File::from_strings("b/3.txt", "x")],
+        );
+        assert_eq!(paths(&pairs), vec![("b/2.txt", "b/3.txt")]);
+        assert_eq!(leftover_f1s[0].path.to_str(), Some("a/1.txt"));
+        assert!(leftover_f2s.is_empty());
+    }
+
+    #[test]
+    fn assigns_by_name_for_large_groups() {
+        let f1s = vec![
+            File::from_strings("a/logo.png", "x"),
+            File::from_strings("x/y.txt", "x"),
+            File::from_strings("z/other/logo.png", "x"),
+        ];
+        let f2s = vec![
+            File::from_strings("q.txt", "x"),
+            File::from_strings("z/other/img/logo.png", "x"),
+        ];
+        assert_eq!(
+            assign_by_name(&f1s, &f2s, &|_, _| true),
+            vec![Some(0), Some(2)]
+        );
+    }
+}
@@ -0,0 +1,196 @@
+use crate::error::Error;
+use clap::ValueEnum;
+use regex::Regex;
+use std::path;
+use unicode_normalization::UnicodeNormalization;
+
+// Determines the keys under which files are matched up between both
+// snapshots. Paths from snapshot 1 can be rewritten before matching,
+// so that renamed hierarchies are compared as if they were in place.
+// Paths from both snapshots can be normalised, so that different
+// spellings of the same path are considered equivalent.
+#[derive(Debug, Clone)]
+pub struct PathKeys {
+    mappings: Vec<Mapping>,
+    unicode_form: Option<UnicodeForm>,
+    ignore_case: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum UnicodeForm {
+    /// Canonical composition (as on most Linux file systems).
+    Nfc,
+    /// Canonical decomposition (as on HFS+).
+    Nfd,
+}
+
+#[derive(Debug, Clone)]
+pub enum Mapping {
+    // Replaces the leading path components.
+    Prefix(path::PathBuf, path::PathBuf),
+    // Replaces the first match in the path.
+    Regex(Regex, String),
+}
+
+impl Mapping {
+    // Parses a rule of the form `OLD=NEW`.
+    pub fn parse_prefix(rule: &str) -> Result<Mapping, Error> {
+        let (old, new) = split_rule(rule)?;
+        return Ok(Mapping::Prefix(
+            path::PathBuf::from(old),
+            path::PathBuf::from(new),
+        ));
+    }
+
+    // Parses a rule of the form `PATTERN=REPLACEMENT`.
+    pub fn parse_regex(rule: &str) -> Result<Mapping, Error> {
+        let (pattern, replacement) = split_rule(rule)?;
+        let re = Regex::new(pattern).map_err(|e| {
+            return Error::from(format!("invalid regex: {}", pattern), e.to_string());
+        })?;
+        return Ok(Mapping::Regex(re, replacement.to_string()));
+    }
+
+    fn apply(&self, p: &path::Path) -> Option<path::PathBuf> {
+        return match self {
+            Mapping::Prefix(old, new) => p.strip_prefix(old).ok().map(|rest| new.join(rest)),
+            Mapping::Regex(re, replacement) => p
+                .to_str()
+                .filter(|s| re.is_match(s))
+                .map(|s| path::PathBuf::from(re.replace(s, replacement).as_ref())),
+        };
+    }
+}
+
+fn split_rule(rule: &str) -> Result<(&str, &str), Error> {
+    return rule
+        .split_once('=')
+        .filter(|(old, _)| !old.is_empty())
+        .ok_or_else(|| Error::new(format!("invalid mapping (expected OLD=NEW): {}", rule)));
+}
+
+impl PathKeys {
+    pub fn new(
+        mappings: Vec<Mapping>,
+        unicode_form: Option<UnicodeForm>,
+        ignore_case: bool,
+    ) -> PathKeys {
+        return PathKeys {
+            mappings,
+            unicode_form,
+            ignore_case,
+        };
+    }
+
+    // The key of a path from snapshot 1. The first matching mapping
+    // is applied.
+    pub fn snap_1(&self, p: &path::Path) -> path::PathBuf {
+        return self
+            .mappings
+            .iter()
+            .find_map(|m| m.apply(p))
+            .map(|mapped| self.snap_2(&mapped))
+            .unwrap_or_else(|| self.snap_2(p));
+    }
+
+    // The key of a path from snapshot 2.
+    pub fn snap_2(&self, p: &path::Path) -> path::PathBuf {
+        if self.unicode_form.is_none() && !self.ignore_case {
+            return p.to_path_buf();
+        }
+        // Paths that aren’t valid UTF-8 cannot be normalised.
+        let s = match p.to_str() {
+            Some(s) => s,
+            None => return p.to_path_buf(),
+        };
+        let normalised: String = match self.unicode_form {
+            Some(UnicodeForm::Nfc) => s.nfc().collect(),
+            Some(UnicodeForm::Nfd) => s.nfd().collect(),
+            None => s.to_string(),
+        };
+        if self.ignore_case {
+            return path::PathBuf::from(normalised.to_lowercase());
+        }
+        return path::PathBuf::from(normalised);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::path_key::{Mapping, PathKeys, UnicodeForm};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn prefix_mapping() {
+        let keys = PathKeys::new(
+            vec![Mapping::parse_prefix("Clients=Customers").unwrap()],
+            None,
+            false,
+        );
+        assert_eq!(
+            keys.snap_1(Path::new("Clients/acme/a.txt")),
+            PathBuf::from("Customers/acme/a.txt")
+        );
+        assert_eq!(
+            keys.snap_1(Path::new("ClientsOld/a.txt")),
+            PathBuf::from("ClientsOld/a.txt")
+        );
+        assert_eq!(
+            keys.snap_1(Path::new("x/Clients/a.txt")),
+            PathBuf::from("x/Clients/a.txt")
+        );
+    }
+
+    #[test]
+    fn regex_mapping() {
+        let keys = PathKeys::new(
+            vec![
+                Mapping::parse_regex(r"^(\d{4})-(\d{2})/=$1/$2/").unwrap(),
+                Mapping::parse_prefix("2023=archive/2023").unwrap(),
+            ],
+            None,
+            false,
+        );
+        assert_eq!(
+            keys.snap_1(Path::new("2023-09/img.png")),
+            PathBuf::from("2023/09/img.png")
+        );
+        assert_eq!(
+            keys.snap_1(Path::new("2023/img.png")),
+            PathBuf::from("archive/2023/img.png")
+        );
+    }
+
+    #[test]
+    fn normalisation() {
+        let nfc = "Caf\u{e9}/R\u{e9}sum\u{e9}.txt";
+        let nfd = "Cafe\u{301}/Re\u{301}sume\u{301}.txt";
+
+        let keys = PathKeys::new(vec![], Some(UnicodeForm::Nfc), false);
+        assert_eq!(keys.snap_2(Path::new(nfd)), PathBuf::from(nfc));
+        assert_eq!(keys.snap_2(Path::new(nfc)), PathBuf::from(nfc));
+
+        let keys = PathKeys::new(vec![], Some(UnicodeForm::Nfd), true);
+        assert_eq!(
+            keys.snap_2(Path::new(nfc)),
+            PathBuf::from(nfd.to_lowercase())
+        );
+
+        let keys = PathKeys::new(vec![Mapping::parse_prefix("Old=New").unwrap()], None, true);
+        assert_eq!(
+            keys.snap_1(Path::new("Old/A.TXT")),
+            PathBuf::from("new/a.txt")
+        );
+        assert_eq!(
+            keys.snap_2(Path::new("Old/A.TXT")),
+            PathBuf::from("old/a.txt")
+        );
+    }
+
+    #[test]
+    fn invalid_mappings() {
+        assert!(Mapping::parse_prefix("Clients").is_err());
+        assert!(Mapping::parse_prefix("=Customers").is_err());
+        assert!(Mapping::parse_regex("(=x").is_err());
+    }
+}
@@ -0,0 +1,414 @@
+use crate::error::Error;
+use crate::file::SizeBytes;
+use crate::format::{dec, parse_size};
+use crate::glob::Glob;
+use crate::report::Report;
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use std::path;
+
+// The kinds of changes that policies (and baselines) can refer to, as
+// named in the summary of the JSON output.
+pub const CATEGORIES: [&str; 12] = [
+    "moved",
+    "copied",
+    "added",
+    "deleted",
+    "modified",
+    "appended",
+    "truncated",
+    "moved_modified",
+    "relocated",
+    "type_changed",
+    "metadata_changed",
+    "xattrs_changed",
+];
+
+// One of the `CATEGORIES`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Category(pub &'static str);
+
+impl Category {
+    // Whether a change of the given category falls under this one. Appended
+    // and truncated files are broken down from the modified ones, so they
+    // are covered by "modified" as well. Moved+modified and relocated files
+    // are not (their paths differ), so they need to be named on their own.
+    pub fn covers(&self, category: &str) -> bool {
+        return self.0 == category
+            || (self.0 == "modified" && ["appended", "truncated"].contains(&category));
+    }
+}
+
+// Rules for which changes are acceptable in which parts of the tree, e.g.
+// that nothing under `photos/` may be deleted or modified.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Policy {
+    #[serde(default, rename = "rule")]
+    rules: Vec<Rule>,
+}
+
+// A rule applies to all changes where either path (as in snapshot 1 or in
+// snapshot 2) matches its glob.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    path: Glob,
+    // If given, all other categories are denied.
+    allow: Option<Vec<Category>>,
+    #[serde(default)]
+    deny: Vec<Category>,
+    // Limits for all changes that the rule applies to.
+    max_files: Option<u64>,
+    #[serde(default, deserialize_with = "size")]
+    max_bytes: Option<SizeBytes>,
+}
+
+#[derive(Debug)]
+pub enum Violation {
+    // A change of a category that is not allowed.
+    Change {
+        rule: String,
+        category: &'static str,
+        path: path::PathBuf,
+    },
+    // A limit that was exceeded.
+    Limit {
+        rule: String,
+        limit: &'static str,
+        max: u64,
+        actual: u64,
+    },
+}
+
+impl Policy {
+    // Parses a policy file, which consists of `[[rule]]` tables, e.g.:
+    //
+    //     [[rule]]
+    //     path = "logs/"
+    //     allow = ["added", "appended"]
+    //     max_bytes = "1G"
+    pub fn parse(text: &str) -> Result<Policy, Error> {
+        return parse_config(text, "policy");
+    }
+
+    // Checks all changes of the report against all rules.
+    pub fn check(&self, report: &Report) -> Vec<Violation> {
+        let mut violations = vec![];
+        let mut totals = vec![(0, 0); self.rules.len()];
+        for_each_change(report, |category, paths, size| {
+            for (i, r) in self.rules.iter().enumerate() {
+                if !paths.iter().any(|p| r.path.matches(p)) {
+                    continue;
+                }
+                totals[i].0 += 1;
+                totals[i].1 += size;
+                let is_allowed = r
+                    .allow
+                    .as_ref()
+                    .map(|a| a.iter().any(|c| c.covers(category)))
+                    .unwrap_or(true);
+                if !is_allowed || r.deny.iter().any(|c| c.covers(category)) {
+                    violations.push(Violation::Change {
+                        rule: r.path.text().to_string(),
+                        category,
+                        path: paths.last().unwrap().to_path_buf(),
+                    });
+                }
+            }
+        });
+        for (r, (files, bytes)) in self.rules.iter().zip(totals) {
+            for (limit, max, actual) in [
+                ("max_files", r.max_files, files),
+                ("max_bytes", r.max_bytes, bytes),
+            ] {
+                match max {
+                    Some(max) if actual > max => violations.push(Violation::Limit {
+                        rule: r.path.text().to_string(),
+                        limit,
+                        max,
+                        actual,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        return violations;
+    }
+}
+
+impl Violation {
+    // A line for the detailed report.
+    pub fn detailed(&self) -> String {
+        return match self {
+            Violation::Change {
+                rule,
+                category,
+                path,
+            } => {
+                format!("{} {} {}", rule, category, path.display())
+            }
+            Violation::Limit {
+                rule,
+                limit,
+                max,
+                actual,
+            } => {
+                format!("{} {}={} {}", rule, limit, max, actual)
+            }
+        };
+    }
+
+    // A human-readable description for the terminal.
+    pub fn describe(&self) -> String {
+        return match self {
+            Violation::Change {
+                rule,
+                category,
+                path,
+            } => {
+                format!("{}: {} {}", rule, category, path.display())
+            }
+            Violation::Limit {
+                rule,
+                limit,
+                max,
+                actual,
+            } => format!(
+                "{}: {} {} exceeded ({})",
+                rule,
+                limit,
+                dec(*max as i128),
+                dec(*actual as i128)
+            ),
+        };
+    }
+}
+
+impl TryFrom<String> for Category {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Category, String> {
+        return CATEGORIES
+            .iter()
+            .find(|c| **c == name)
+            .map(|c| Category(c))
+            .ok_or_else(|| {
+                return format!(
+                    "unknown category: {}, expected one of: {}",
+                    name,
+                    CATEGORIES.join(", ")
+                );
+            });
+    }
+}
+
+impl<'de> Deserialize<'de> for Category {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Category, D::Error> {
+        return Category::try_from(String::deserialize(d)?).map_err(D::Error::custom);
+    }
+}
+
+// Parses a configuration file in TOML format (such as a policy or a
+// baseline), where `what` names the kind of file for error messages.
+pub fn parse_config<T: DeserializeOwned>(text: &str, what: &str) -> Result<T, Error> {
+    return toml::from_str(text).map_err(|e| {
+        let message = match e.span() {
+            Some(s) => format!(
+                "invalid {} in line {}",
+                what,
+                text[..s.start].matches('\n').count() + 1
+            ),
+            None => format!("invalid {}", what),
+        };
+        return Error::from(message, e.message().to_string());
+    });
+}
+
+// Sizes are given either in bytes, or as text with a unit (e.g. "1G").
+fn size<'de, D>(d: D) -> Result<Option<SizeBytes>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Size {
+        Bytes(SizeBytes),
+        Text(String),
+    }
+    return match Size::deserialize(d)? {
+        Size::Bytes(n) => Ok(Some(n)),
+        Size::Text(t) => parse_size(&t)
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("invalid size: {}", t))),
+    };
+}
+
+// Visits all changes, with the paths that are involved (as in snapshot 1
+// and/or snapshot 2) and the size (as in snapshot 2, if present).
+fn for_each_change<F>(report: &Report, mut visit: F)
+where
+    F: FnMut(&'static str, &[&path::Path], SizeBytes),
+{
+    for d in &report.dir_moves {
+        for f2 in &d.files {
+            visit("moved", &[&d.source(f2), &f2.path], f2.size);
+        }
+    }
+    for (f1, f2) in report.moved.pairs().iter().chain(report.respelled.pairs()) {
+        visit("moved", &[&f1.path, &f2.path], f2.size);
+    }
+    for f in report.copied.files().into_iter().flatten() {
+        visit("copied", &[&f.path], f.size);
+    }
+    for f in report.added.files().into_iter().flatten() {
+        visit("added", &[&f.path], f.size);
+    }
+    for f in report.deleted.files().into_iter().flatten() {
+        visit("deleted", &[&f.path], f.size);
+    }
+    let modified_1 = report.modified_snap_1.files().into_iter().flatten();
+    let modified_2 = report.modified_snap_2.files().into_iter().flatten();
+    for (f1, f2) in modified_1.zip(modified_2) {
+        visit("modified", &[&f1.path, &f2.path], f2.size);
+    }
+    for (category, pairs) in [
+        ("appended", &report.appended),
+        ("truncated", &report.truncated),
+        ("relocated", &report.relocated),
+    ] {
+        for (f1, f2) in pairs.pairs() {
+            visit(category, &[&f1.path, &f2.path], f2.size);
+        }
+    }
+    for (f1, f2) in report.metadata_changed.pairs() {
+        visit("metadata_changed", &[&f1.path, &f2.path], f2.size);
+    }
+    for (f1, f2) in report.xattrs_changed.pairs() {
+        visit("xattrs_changed", &[&f1.path, &f2.path], f2.size);
+    }
+    for p in &report.moved_modified {
+        visit("moved_modified", &[&p.f1.path, &p.f2.path], p.f2.size);
+    }
+    for (_, _, p, size) in report.type_changed.changes() {
+        visit("type_changed", &[p], *size);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::policy::Policy;
+    use crate::report::Report;
+
+    #[test]
+    fn parses_policies() {
+        assert!(Policy::parse("[[rule]]\npath = \"a/\"\ndeny = [\"deleted\"]").is_ok());
+        assert!(Policy::parse(
+            "[[rule]]\npath = \"a/\"\ndeny = [\n  \"deleted\",\n  \"modified\",\n]"
+        )
+        .is_ok());
+        assert!(Policy::parse("[[rule]]\npath = \"a/\"\nmax_bytes = 1024").is_ok());
+        assert!(Policy::parse("[[rule]]\npath = \"a/\"\ndeny = [\"xattrs_changed\"]").is_ok());
+        assert!(Policy::parse("").is_ok());
+        for text in [
+            "[[rule]]\ndeny = [\"deleted\"]",
+            "[[rule]]\npath = \"a/\"\ndeny = [\"removed\"]",
+            "[[rule]]\npath = \"a/\"\nmax_bytes = \"1X\"",
+            "[[rule]]\npath = 1",
+            "[[rule]]\npath = \"a/\"\nmax_files = -1",
+            "[[rule]]\npath = \"a/[\"",
+            "[[policy]]\npath = \"a/\"",
+            "[rule]\npath = \"a/\"",
+            "path = \"a/\"",
+        ] {
+            assert!(Policy::parse(text).is_err(), "{}", text);
+        }
+        assert_eq!(
+            Policy::parse("[[rule]]\npath = \"a/\"\n\n[[rule]]\npath = \"b/\"\ndeny = [\"removed\"]")
+                .unwrap_err()
+                .to_string(),
+            "Error: invalid policy in line 6\n(unknown category: removed, expected one of: moved, copied, added, deleted, modified, appended, truncated, moved_modified, relocated, type_changed, metadata_changed, xattrs_changed)"
+        );
+    }
+
+    #[test]
+    fn checks_rules() {
+        let policy = Policy::parse(
+            r#"
+            [[rule]]
+            path = "photos/"
+            deny = ["deleted", "modified"]
+
+            [[rule]]
+            path = "logs/"
+            allow = ["added", "appended"]
+            max_bytes = "1K"
+            "#,
+        )
+        .unwrap();
+
+        let mut r = Report::new();
+        r.deleted.record(&File::from_strings("photos/a.jpg", "a"));
+        r.deleted.record(&File::from_strings("other/b.jpg", "b"));
+        r.added
+            .record(&File::from_strings("logs/1.log", &"x".repeat(2_000)));
+        r.truncated.record(
+            &File::from_strings("logs/2.log", "123"),
+            &File::from_strings("logs/2.log", "1"),
+        );
+        r.moved.record(
+            &File::from_strings("photos/c.jpg", "c"),
+            &File::from_strings("archive/c.jpg", "c"),
+        );
+        r.truncated.record(
+            &File::from_strings("photos/d.jpg", "ddd"),
+            &File::from_strings("photos/d.jpg", "d"),
+        );
+
+        let violations: Vec<String> = policy.check(&r).iter().map(|v| v.detailed()).collect();
+        assert_eq!(
+            violations,
+            vec![
+                "photos/ deleted photos/a.jpg",
+                "logs/ truncated logs/2.log",
+                "photos/ truncated photos/d.jpg",
+                "logs/ max_bytes=1000 2001",
+            ]
+        );
+        assert_eq!(
+            policy.check(&r)[3].describe(),
+            "logs/: max_bytes 1,000 exceeded (2,001)"
+        );
+        assert!(policy.check(&Report::new()).is_empty());
+    }
+
+    #[test]
+    fn covers_broken_down_categories() {
+        let policy = Policy::parse(
+            r#"
+            [[rule]]
+            path = "logs/"
+            allow = ["modified"]
+            "#,
+        )
+        .unwrap();
+
+        let mut r = Report::new();
+        r.appended.record(
+            &File::from_strings("logs/1.log", "1"),
+            &File::from_strings("logs/1.log", "12"),
+        );
+        r.truncated.record(
+            &File::from_strings("logs/2.log", "12"),
+            &File::from_strings("logs/2.log", "1"),
+        );
+        r.relocated.record(
+            &File::from_strings("logs/3.log", "3"),
+            &File::from_strings("logs/old/3.log", "4"),
+        );
+
+        let violations: Vec<String> = policy.check(&r).iter().map(|v| v.detailed()).collect();
+        assert_eq!(violations, vec!["logs/ relocated logs/old/3.log"]);
+    }
+}
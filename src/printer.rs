@@ -12,7 +12,6 @@ pub trait Printer {
 
 #[derive(Debug, Copy, Clone)]
 pub struct Colours {
-    pub blank: &'static str,
     pub reset: &'static str,
     pub bold: &'static str,
     pub light: &'static str,
@@ -31,7 +30,6 @@ pub struct TerminalPrinter {
 }
 
 const WITH_COLOURS: Colours = Colours {
-    blank: "",
     reset: "\x1b[0m",
     bold: "\x1b[1m",
     light: "\x1b[38;5;253m",
@@ -45,7 +43,6 @@ const WITH_COLOURS: Colours = Colours {
 };
 
 const NO_COLOURS: Colours = Colours {
-    blank: "",
     reset: "",
     bold: "",
     light: "",
@@ -90,7 +87,7 @@ pub struct FilePrinter {
 
 impl FilePrinter {
     pub fn new(p: &path::Path) -> Result<FilePrinter, Error> {
-        let target_file = fs::File::create(&p)?;
+        let target_file = fs::File::create(p)?;
         return Ok(FilePrinter { target_file });
     }
 }
@@ -11,31 +11,31 @@ use std::time::Instant;
 #[derive(Debug)]
 pub struct Progress<P: Printer> {
     printer: P,
-    display_name: &'static str,
+    display_name: String,
     initialised: Instant,
     last_trigger: Instant,
     bytes_since_last_trigger: SizeBytes,
     current: Count,
     expected: Count,
     previous_files_count: Option<Count>,
+    // Files whose extended attributes couldn’t be read (and therefore
+    // aren’t compared).
+    unreadable_xattrs: u64,
 }
 
 impl<P: Printer> Progress<P> {
-    pub fn new(
-        printer: P,
-        display_name: &'static str,
-        previous_files_count: Option<Count>,
-    ) -> Progress<P> {
+    pub fn new(printer: P, display_name: &str, previous_files_count: Option<Count>) -> Progress<P> {
         let init = Instant::now();
         return Progress {
             printer,
-            display_name,
+            display_name: display_name.to_string(),
             initialised: init,
             last_trigger: init,
             bytes_since_last_trigger: 0,
             current: Count::new(),
             expected: Count::new(),
             previous_files_count,
+            unreadable_xattrs: 0,
         };
     }
 
@@ -52,9 +52,10 @@ impl<P: Printer> Progress<P> {
     pub fn scan_done(&mut self, scheduled: Count, skipped: SkippedStats) {
         self.expected = scheduled;
         let skipped_info = {
-            let text = vec![
+            let text = [
                 (skipped.dot_paths, "dot-path"),
                 (skipped.symlinks, "symlink"),
+                (skipped.symlink_loops, "symlink loop"),
                 (skipped.no_opener, "unopenable path"),
             ]
             .iter()
@@ -100,19 +101,27 @@ impl<P: Printer> Progress<P> {
         if self.initialised != self.last_trigger && elapsed_ms < 666 {
             return;
         }
-        let rate = if elapsed_ms != 0 {
-            let s = (1000 * self.bytes_since_last_trigger as u128 / elapsed_ms) as SizeBytes;
-            format!("[{: >7}/s]", size_human(s))
-        } else {
-            "".to_string()
-        };
+        let rate = (1000 * self.bytes_since_last_trigger as u128)
+            .checked_div(elapsed_ms)
+            .map(|s| format!("[{: >7}/s]", size_human(s as SizeBytes)))
+            .unwrap_or_default();
         self.print_process(rate);
         self.last_trigger = Instant::now();
         self.bytes_since_last_trigger = 0;
     }
 
+    pub fn process_skip_xattrs(&mut self) {
+        self.unreadable_xattrs += 1;
+    }
+
     pub fn process_done(&mut self) {
-        self.print_process("           ".to_string());
+        let skipped_info = match self.unreadable_xattrs {
+            0 => "".to_string(),
+            1 => "(Skipped: 1 file with unreadable xattrs)".to_string(),
+            n => format!("(Skipped: {} files with unreadable xattrs)", dec(n as i128)),
+        };
+        // Overwrites the rate, which is 11 characters long.
+        self.print_process(format!("{: <11}", skipped_info));
         self.printer.print("\n".to_string());
     }
 
@@ -192,6 +201,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn print_process_done_with_skipped_files() {
+        let p = MockPrinter::new();
+        let mut progress = Progress::new(p, "Snap", None);
+        let count = {
+            let mut c = Count::new();
+            c.add(3, 910);
+            c
+        };
+        progress.scan_done(count, SkippedStats::new());
+        progress.process_inc(3, 910);
+        progress.printer.flush();
+        progress.process_skip_xattrs();
+        progress.process_skip_xattrs();
+        progress.process_done();
+        assert_eq!(
+            progress.printer.flush(),
+            "\r      Processing:      3 files    910 B   100 %     0s   (Skipped: 2 files with unreadable xattrs)\n"
+        );
+    }
+
     #[test]
     fn print_scan_done() {
         let p = MockPrinter::new();
@@ -227,6 +257,13 @@ mod tests {
             progress.printer.flush(),
             "\rSnap: Indexed:     12,345 files  999.8 K   (Skipped: 4,123 dot-paths, 2,223 symlinks, 9,876 unopenable paths)\n"
         );
+
+        skipped.symlink_loops += 1;
+        progress.scan_done(count, skipped);
+        assert_eq!(
+            progress.printer.flush(),
+            "\rSnap: Indexed:     12,345 files  999.8 K   (Skipped: 4,123 dot-paths, 2,223 symlinks, 1 symlink loop, 9,876 unopenable paths)\n"
+        );
     }
 
     #[test]
@@ -0,0 +1,114 @@
+use crate::file::{File, SizeBytes};
+use crate::pairing;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path;
+
+// Which part of the file name must be the same, in order to consider a
+// deleted and an added file as relocated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum RelocateBy {
+    /// The entire file name, including its extension.
+    Name,
+    /// The file name without its extension.
+    Stem,
+}
+
+// Pairs up deleted and added files by their names (as a cheap heuristic,
+// regardless of their contents). If `size_tolerance_percent` is given,
+// the file sizes must not differ by more than that. Returns the pairs,
+// and the deleted and added files that couldn’t be paired up.
+pub fn pair_relocated(
+    deleted: Vec<File>,
+    added: Vec<File>,
+    by: RelocateBy,
+    size_tolerance_percent: Option<u64>,
+) -> (Vec<(File, File)>, Vec<File>, Vec<File>) {
+    let mut groups: HashMap<OsString, (Vec<File>, Vec<File>)> = HashMap::new();
+    let mut unpaired_1 = vec![];
+    let mut unpaired_2 = vec![];
+    for f1 in deleted {
+        match key(&f1.path, by) {
+            Some(k) => groups.entry(k).or_default().0.push(f1),
+            None => unpaired_1.push(f1),
+        }
+    }
+    for f2 in added {
+        match key(&f2.path, by) {
+            Some(k) => groups.entry(k).or_default().1.push(f2),
+            None => unpaired_2.push(f2),
+        }
+    }
+
+    let mut pairs = vec![];
+    for (_, (f1s, f2s)) in groups {
+        let (ps, u1, u2) = pairing::pair_up_where(f1s, f2s, |f1, f2| {
+            return size_tolerance_percent
+                .map(|t| is_similar_size(f1.size, f2.size, t))
+                .unwrap_or(true);
+        });
+        pairs.extend(ps);
+        unpaired_1.extend(u1);
+        unpaired_2.extend(u2);
+    }
+    pairs.sort_by(|(_, a), (_, b)| a.path.cmp(&b.path));
+    return (pairs, unpaired_1, unpaired_2);
+}
+
+fn key(p: &path::Path, by: RelocateBy) -> Option<OsString> {
+    let k = match by {
+        RelocateBy::Name => p.file_name(),
+        RelocateBy::Stem => p.file_stem(),
+    };
+    return k.map(|k| k.to_os_string());
+}
+
+fn is_similar_size(s1: SizeBytes, s2: SizeBytes, tolerance_percent: u64) -> bool {
+    let (small, large) = (s1.min(s2) as u128, s1.max(s2) as u128);
+    return (large - small) * 100 <= large * tolerance_percent as u128;
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::relocation::{pair_relocated, RelocateBy};
+
+    #[test]
+    fn pairs_by_name() {
+        let (pairs, deleted, added) = pair_relocated(
+            vec![
+                File::from_strings("docs/report.docx", "1234567890"),
+                File::from_strings("docs/notes.txt", "1234567890"),
+            ],
+            vec![
+                File::from_strings("archive/docs/report.docx", "123456789"),
+                File::from_strings("archive/docs/notes.md", "1234567890"),
+            ],
+            RelocateBy::Name,
+            None,
+        );
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].0.path.to_str(), Some("docs/report.docx"));
+        assert_eq!(pairs[0].1.path.to_str(), Some("archive/docs/report.docx"));
+        assert_eq!(deleted.len(), 1);
+        assert_eq!(added.len(), 1);
+    }
+
+    #[test]
+    fn pairs_by_stem_and_size() {
+        let deleted = vec![
+            File::from_strings("a/notes.txt", "1234567890"),
+            File::from_strings("b/report.txt", "1234567890"),
+        ];
+        let added = vec![
+            File::from_strings("x/notes.md", "123456789"),
+            File::from_strings("y/report.md", "12345"),
+        ];
+        let (pairs, deleted, added) = pair_relocated(deleted, added, RelocateBy::Stem, Some(10));
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].1.path.to_str(), Some("x/notes.md"));
+        assert_eq!(deleted[0].path.to_str(), Some("b/report.txt"));
+        assert_eq!(added[0].path.to_str(), Some("y/report.md"));
+    }
+}
@@ -1,18 +1,73 @@
+use crate::dir_move::DirMove;
+use crate::export::{Export, Section};
+use crate::file::{File, SizeBytes};
 use crate::format::{dec, dec_signed};
+use crate::group::{Group, CATEGORIES};
+use crate::histogram::{Histograms, AGE_BUCKETS, SIZE_BUCKETS};
+use crate::meta::MetaChanges;
+use crate::policy::Violation;
 use crate::printer::{Colours, Printer, TerminalPrinter, SNP1, SNP2};
+use crate::similarity::SimilarPair;
 use crate::stats;
-use stats::Stats;
+use crate::table::{
+    print_breakdown_table, print_file_lists, print_histogram_table, print_summary_table, Row,
+};
+use crate::top::TopFiles;
+use stats::{Count, DirStats, PairStats, Stats, TypeChanges};
+use std::collections::HashMap;
+use std::path;
 
 #[derive(Debug)]
 pub struct Report {
     pub total_snap_1: Stats,
     pub total_snap_2: Stats,
+    // FIFOs, sockets and device nodes (included in the totals).
+    pub special_snap_1: u64,
+    pub special_snap_2: u64,
     pub identical: Stats,
-    pub moved: Stats,
+    pub metadata_changed: MetaChanges,
+    pub xattrs_changed: PairStats,
+    pub moved: PairStats,
+    pub dir_moves: Vec<DirMove>,
+    pub respelled: PairStats,
+    pub copied: Stats,
     pub added: Stats,
+    pub added_empty: Count,
     pub deleted: Stats,
+    pub deleted_empty: Count,
     pub modified_snap_1: Stats,
     pub modified_snap_2: Stats,
+    // Modified files whose size and modification time are unchanged.
+    pub suspicious: Stats,
+    pub appended: PairStats,
+    pub truncated: PairStats,
+    // Estimated changed bytes of modified files, in total and by path
+    // (only if block comparison is enabled).
+    pub changed_bytes_total: Option<SizeBytes>,
+    pub changed_bytes: HashMap<path::PathBuf, SizeBytes>,
+    // Modified files that couldn’t be compared block by block.
+    pub changed_bytes_skipped: u64,
+    pub moved_modified: Vec<SimilarPair>,
+    pub moved_modified_snap_1: Count,
+    pub moved_modified_snap_2: Count,
+    pub relocated: PairStats,
+    pub relocated_snap_1: Count,
+    pub type_changed: TypeChanges,
+    pub dirs: DirStats,
+    // The changes that match the baseline (if one is given), by category.
+    // They aren’t included in any other category.
+    pub expected: Option<Vec<(&'static str, Count)>>,
+    // The summary, broken down by directory, by file extension and by
+    // file type (each only if requested).
+    pub dir_groups: Option<Vec<Group>>,
+    pub ext_groups: Option<Vec<Group>>,
+    pub type_groups: Option<Vec<Group>>,
+    // The distribution of sizes and ages (if requested).
+    pub histograms: Option<Histograms>,
+    // The largest added, deleted and modified files (if requested).
+    pub top: Option<TopFiles>,
+    // The changes that violate the policy (if one is given).
+    pub policy_violations: Option<Vec<Violation>>,
 }
 
 impl Report {
@@ -20,15 +75,154 @@ impl Report {
         return Report {
             total_snap_1: Stats::new(),
             total_snap_2: Stats::new(),
+            special_snap_1: 0,
+            special_snap_2: 0,
             identical: Stats::new(),
-            moved: Stats::new_with_file_storage(),
+            metadata_changed: MetaChanges::new(),
+            xattrs_changed: PairStats::new(),
+            moved: PairStats::new(),
+            dir_moves: vec![],
+            respelled: PairStats::new(),
+            copied: Stats::new_with_file_storage(),
             added: Stats::new_with_file_storage(),
+            added_empty: Count::new(),
             deleted: Stats::new_with_file_storage(),
+            deleted_empty: Count::new(),
             modified_snap_1: Stats::new(),
             modified_snap_2: Stats::new_with_file_storage(),
+            suspicious: Stats::new_with_file_storage(),
+            appended: PairStats::new(),
+            truncated: PairStats::new(),
+            changed_bytes_total: None,
+            changed_bytes: HashMap::new(),
+            changed_bytes_skipped: 0,
+            moved_modified: vec![],
+            moved_modified_snap_1: Count::new(),
+            moved_modified_snap_2: Count::new(),
+            relocated: PairStats::new(),
+            relocated_snap_1: Count::new(),
+            type_changed: TypeChanges::new(),
+            dirs: DirStats::new(),
+            expected: None,
+            dir_groups: None,
+            ext_groups: None,
+            type_groups: None,
+            histograms: None,
+            top: None,
+            policy_violations: None,
         };
     }
 
+    // Also stores the identical files, which are needed for breaking down
+    // the summary.
+    pub fn new_with_identical_files() -> Report {
+        let mut r = Report::new();
+        r.identical = Stats::new_with_file_storage();
+        return r;
+    }
+
+    // Also stores the snapshot-1 versions of the modified files, which are
+    // needed for ranking them, and for matching their paths against rules.
+    pub fn with_modified_snap_1_files(mut self) -> Report {
+        self.modified_snap_1 = Stats::new_with_file_storage();
+        return self;
+    }
+
+    // The counts of all summary categories, by their machine-readable
+    // names.
+    pub fn summary_counts(&self) -> Vec<(&'static str, Count)> {
+        return vec![
+            ("snap_1", self.total_snap_1.count),
+            ("snap_2", self.total_snap_2.count),
+            ("identical", self.identical.count),
+            ("metadata_changed", self.metadata_changed.count),
+            ("xattrs_changed", self.xattrs_changed.count),
+            ("moved", self.moved.count),
+            ("respelled", self.respelled.count),
+            ("copied", self.copied.count),
+            ("added", self.added.count),
+            ("deleted", self.deleted.count),
+            ("modified", self.modified_snap_2.count),
+            ("suspicious", self.suspicious.count),
+            ("appended", self.appended.count),
+            ("truncated", self.truncated.count),
+            ("moved_modified", self.moved_modified_snap_2),
+            ("relocated", self.relocated.count),
+            ("type_changed", self.type_changed.count),
+            ("expected", self.expected_total()),
+            (
+                "folders_snap_1",
+                entries_without_size(self.dirs.total_snap_1),
+            ),
+            (
+                "folders_snap_2",
+                entries_without_size(self.dirs.total_snap_2),
+            ),
+            (
+                "folders_added",
+                entries_without_size(self.dirs.added.len() as u64),
+            ),
+            (
+                "folders_deleted",
+                entries_without_size(self.dirs.deleted.len() as u64),
+            ),
+        ];
+    }
+
+    // Whether the snapshots differ in any way (other than in the files
+    // that aren’t compared, e.g. because they are filtered out).
+    pub fn has_differences(&self) -> bool {
+        return self.summary_counts().iter().any(|(name, c)| {
+            return !matches!(*name, "snap_1" | "snap_2" | "identical" | "expected")
+                && !name.starts_with("folders_snap_")
+                && c.files > 0;
+        });
+    }
+
+    fn expected_total(&self) -> Count {
+        let mut total = Count::new();
+        for (_, c) in self.expected.iter().flatten() {
+            total.add(c.files, c.size);
+        }
+        return total;
+    }
+
+    // The summary (and its breakdowns) in machine-readable form.
+    pub fn export(&self) -> Export {
+        let mut e = Export::new(self.summary_counts());
+        for (name, key_name, groups) in [
+            ("dirs", "dir", &self.dir_groups),
+            ("extensions", "extension", &self.ext_groups),
+            ("types", "type", &self.type_groups),
+        ] {
+            if let Some(groups) = groups {
+                let mut s = Section::new(name, key_name);
+                for g in groups {
+                    s.add_row(
+                        g.key.clone(),
+                        CATEGORIES.iter().copied().zip(g.counts).collect(),
+                    );
+                }
+                e.add_section(s);
+            }
+        }
+        if let Some(h) = &self.histograms {
+            let mut sizes = Section::new("sizes", "bucket");
+            for (i, (label, _)) in SIZE_BUCKETS.iter().enumerate() {
+                let counts = (0..CATEGORIES.len()).map(|c| (CATEGORIES[c], h.size[c][i]));
+                sizes.add_row(label.to_string(), counts.collect());
+            }
+            e.add_section(sizes);
+            let mut ages = Section::new("ages", "bucket");
+            for (i, (label, _)) in AGE_BUCKETS.iter().enumerate() {
+                let counts = (0..CATEGORIES.len()).map(|c| (CATEGORIES[c], h.age[c][i]));
+                ages.add_row(label.to_string(), counts.collect());
+            }
+            e.add_section(ages);
+        }
+        return e;
+    }
+
     pub fn detailed_list(&self, printer: &mut dyn Printer) {
         printer.print(format!(
             "#sn1 {} ({} files)\n",
@@ -42,8 +236,54 @@ impl Report {
             "=idn {} ({} files)\n",
             self.identical.count.size, self.identical.count.files
         ));
-        for f in self.moved.files().unwrap() {
-            printer.print(format!(">mvd {} {}\n", f.size, f.path.display()));
+        if self.expected.is_some() {
+            let total = self.expected_total();
+            printer.print(format!("=exp {} ({} files)\n", total.size, total.files));
+        }
+        for (f1, f2) in self.metadata_changed.pairs() {
+            let (m1, m2) = (f1.meta.unwrap(), f2.meta.unwrap());
+            let changes: Vec<String> = m1
+                .diff(&m2)
+                .into_iter()
+                .map(|a| format!("{}={}->{}", a.name(), m1.show(a), m2.show(a)))
+                .collect();
+            printer.print(format!(
+                "=mta {} {} {}\n",
+                f2.size,
+                changes.join(","),
+                f2.path.display()
+            ));
+        }
+        for (f1, f2) in self.xattrs_changed.pairs() {
+            printer.print(format!(
+                "=xat {} {} {}\n",
+                f2.size,
+                changed_xattrs(f1, f2).join(","),
+                f2.path.display()
+            ));
+        }
+        for d in &self.dir_moves {
+            printer.print(format!(
+                ">dmv {} ({} files) {} -> {}\n",
+                d.count.size,
+                d.count.files,
+                d.from.display(),
+                d.to.display()
+            ));
+        }
+        for (f1, _) in self.moved.pairs() {
+            printer.print(format!(">mvd {} {}\n", f1.size, f1.path.display()));
+        }
+        for (f1, f2) in self.respelled.pairs() {
+            printer.print(format!(
+                ">rsp {} {} -> {}\n",
+                f2.size,
+                f1.path.display(),
+                f2.path.display()
+            ));
+        }
+        for f in self.copied.files().unwrap() {
+            printer.print(format!("+cpy {} {}\n", f.size, f.path.display()));
         }
         for f in self.added.files().unwrap() {
             printer.print(format!("+add {} {}\n", f.size, f.path.display()));
@@ -52,93 +292,366 @@ impl Report {
             printer.print(format!("-del {} {}\n", f.size, f.path.display()));
         }
         for f in self.modified_snap_2.files().unwrap() {
-            printer.print(format!("*mdf {} {}\n", f.size, f.path.display()));
+            match self.changed_bytes.get(&f.path) {
+                Some(c) => printer.print(format!("*mdf {} ~{} {}\n", f.size, c, f.path.display())),
+                None => printer.print(format!("*mdf {} {}\n", f.size, f.path.display())),
+            }
+        }
+        for f in self.suspicious.files().unwrap() {
+            printer.print(format!("!sus {} {}\n", f.size, f.path.display()));
+        }
+        for (f1, f2) in self.appended.pairs() {
+            printer.print(format!(
+                "*apd {} +{} {}\n",
+                f2.size,
+                f2.size - f1.size,
+                f2.path.display()
+            ));
+        }
+        for (f1, f2) in self.truncated.pairs() {
+            printer.print(format!(
+                "*trc {} -{} {}\n",
+                f2.size,
+                f1.size - f2.size,
+                f2.path.display()
+            ));
+        }
+        for p in &self.moved_modified {
+            printer.print(format!(
+                "~mmd {} {}% {} -> {}\n",
+                p.f2.size,
+                p.percent,
+                p.f1.path.display(),
+                p.f2.path.display()
+            ));
+        }
+        // These pairs are only matched by name, their contents is unrelated.
+        for (f1, f2) in self.relocated.pairs() {
+            printer.print(format!(
+                "?rlc {} {} -> {}\n",
+                f2.size,
+                f1.path.display(),
+                f2.path.display()
+            ));
+        }
+        for (from, to, p, size) in self.type_changed.changes() {
+            printer.print(format!("*typ {} {}->{} {}\n", size, from, to, p.display()));
+        }
+        for v in self.policy_violations.iter().flatten() {
+            printer.print(format!("!pol {}\n", v.detailed()));
+        }
+        for (prefix, dirs) in [
+            ("+dir", &self.dirs.added),
+            ("-dir", &self.dirs.deleted),
+            ("#em1", &self.dirs.empty_snap_1),
+            ("#em2", &self.dirs.empty_snap_2),
+        ] {
+            for d in dirs {
+                printer.print(format!("{} {}\n", prefix, d.display()));
+            }
         }
     }
 
     pub fn summary(&self, mut printer: TerminalPrinter) {
-        let files = vec![
-            "FILES".to_string(),
-            dec(self.total_snap_1.count.files as i128),
-            dec(self.total_snap_2.count.files as i128),
-            dec(self.identical.count.files as i128),
-            dec(self.moved.count.files as i128),
-            dec(self.added.count.files as i128),
-            dec(self.deleted.count.files as i128),
-            dec(self.modified_snap_2.count.files as i128),
-        ];
-        let size = vec![
-            "BYTES".to_string(),
-            dec(self.total_snap_1.count.size as i128),
-            dec(self.total_snap_2.count.size as i128),
-            dec(self.identical.count.size as i128),
-            dec(self.moved.count.size as i128),
-            dec(self.added.count.size as i128),
-            dec(self.deleted.count.size as i128),
-            dec(self.modified_snap_2.count.size as i128),
-        ];
-        let longest_size = size.iter().map(|s| s.len()).max().unwrap();
-        let longest_file_count = files.iter().map(|s| s.len()).max().unwrap();
-        let byte_markers = {
-            let markers = "T   G   M   K   B";
-            markers[markers.len() - longest_size..].to_string()
-        };
-        let modified_delta = {
-            let delta =
-                self.modified_snap_2.count.size as i128 - self.modified_snap_1.count.size as i128;
-            if delta == 0 {
-                "±0".to_string()
-            } else {
-                dec_signed(delta)
-            }
-        };
         let Colours {
-            blank: ___,
-            dark: drk,
             yellow: ylw,
-            brown: brn,
             light: lgt,
             blue: blu,
             green: grn,
             red,
-            reset: rst,
-            bold: bld,
             ..
         } = printer.colours;
-        printer.print(format!(
-            "
-{bld}            {___}{___}            {: >f$}     {: >b$}{rst}
-{bld}            {rst}{drk}            {: >f$}     {: >b$}{rst}
-{bld}TOTAL       {rst}{lgt}{SNP1}      {: >f$}     {: >b$}{rst}
-{bld}            {rst}{lgt}{SNP2}      {: >f$}     {: >b$}{rst}
-{bld}            {rst}{lgt}
-{bld}OF WHICH    {rst}{blu}Identical   {: >f$}     {: >b$}{rst}
-{bld}            {rst}{blu}Moved       {: >f$}     {: >b$}{rst}
-{bld}            {rst}{grn}Added       {: >f$}     {: >b$}{rst}
-{bld}            {rst}{red}Deleted     {: >f$}     {: >b$}{rst}
-{bld}            {rst}{ylw}Modified    {: >f$}     {: >b$}{brn} ({}){rst}
-",
-            files[0],
-            size[0],
-            "".to_string(),
-            byte_markers,
-            files[1],
-            size[1],
-            files[2],
-            size[2],
-            files[3],
-            size[3],
-            files[4],
-            size[4],
-            files[5],
-            size[5],
-            files[6],
-            size[6],
-            files[7],
-            size[7],
-            modified_delta,
-            b = longest_size,
-            f = longest_file_count,
+        let mut rows = vec![];
+        for (section, label, total, special) in [
+            ("TOTAL", SNP1, self.total_snap_1.count, self.special_snap_1),
+            ("", SNP2, self.total_snap_2.count, self.special_snap_2),
+        ] {
+            rows.push(Row::new(section, lgt, label, total));
+            if special > 0 {
+                rows.push(
+                    Row::sub(lgt, "Special files", entries_without_size(special)).without_size(),
+                );
+            }
+        }
+        rows.extend([
+            Row::blank(),
+            Row::new("OF WHICH", blu, "Identical", self.identical.count),
+        ]);
+        if self.metadata_changed.count.files > 0 {
+            rows.push(
+                Row::sub(blu, "Metadata changed", self.metadata_changed.count)
+                    .with_note(self.metadata_changed.summary()),
+            );
+        }
+        if self.xattrs_changed.count.files > 0 {
+            let mut names: Vec<String> = self
+                .xattrs_changed
+                .pairs()
+                .iter()
+                .flat_map(|(f1, f2)| changed_xattrs(f1, f2))
+                .collect();
+            names.sort();
+            names.dedup();
+            if names.len() > 3 {
+                names.truncate(3);
+                names.push("…".to_string());
+            }
+            rows.push(
+                Row::sub(blu, "Xattrs changed", self.xattrs_changed.count)
+                    .with_note(names.join(", ")),
+            );
+        }
+        rows.push(Row::new("", blu, "Moved", self.moved.count));
+        if !self.dir_moves.is_empty() {
+            let mut count = Count::new();
+            for d in &self.dir_moves {
+                count.add(d.count.files, d.count.size);
+            }
+            let folders = self.dir_moves.len();
+            rows.push(Row::sub(blu, "In folders", count).with_note(format!(
+                "{} folder{}",
+                dec(folders as i128),
+                if folders == 1 { "" } else { "s" }
+            )));
+        }
+        if self.respelled.count.files > 0 {
+            rows.push(Row::sub(blu, "Respelled", self.respelled.count));
+        }
+        rows.push(Row::new("", blu, "Copied", self.copied.count));
+        rows.push(Row::new("", grn, "Added", self.added.count));
+        if self.added_empty.files > 0 {
+            rows.push(Row::sub(grn, "Empty", self.added_empty));
+        }
+        rows.push(Row::new("", red, "Deleted", self.deleted.count));
+        if self.deleted_empty.files > 0 {
+            rows.push(Row::sub(red, "Empty", self.deleted_empty));
+        }
+        rows.push(
+            Row::new("", ylw, "Modified", self.modified_snap_2.count).with_note({
+                let delta = size_delta(&self.modified_snap_1.count, &self.modified_snap_2.count);
+                match self.changed_bytes_total {
+                    Some(c) if self.changed_bytes_skipped > 0 => format!(
+                        "{}; ~{} changed, {} skipped",
+                        delta,
+                        dec(c as i128),
+                        dec(self.changed_bytes_skipped as i128)
+                    ),
+                    Some(c) => format!("{}; ~{} changed", delta, dec(c as i128)),
+                    None => delta,
+                }
+            }),
+        );
+        if self.suspicious.count.files > 0 {
+            rows.push(
+                Row::sub(red, "Suspicious", self.suspicious.count)
+                    .with_note("possible corruption".to_string()),
+            );
+        }
+        for (label, pairs) in [("Appended", &self.appended), ("Truncated", &self.truncated)] {
+            if pairs.count.files > 0 {
+                rows.push(Row::sub(ylw, label, pairs.count).with_note(pair_delta(pairs)));
+            }
+        }
+        if !self.moved_modified.is_empty() {
+            rows.push(
+                Row::new("", ylw, "Moved+Modified", self.moved_modified_snap_2).with_note(
+                    size_delta(&self.moved_modified_snap_1, &self.moved_modified_snap_2),
+                ),
+            );
+        }
+        if self.relocated.count.files > 0 {
+            rows.push(
+                Row::new("", ylw, "Relocated", self.relocated.count).with_note(format!(
+                    "content changed, matched by name; {}",
+                    size_delta(&self.relocated_snap_1, &self.relocated.count)
+                )),
+            );
+        }
+        if self.type_changed.count.files > 0 {
+            rows.push(Row::new("", ylw, "Type changed", self.type_changed.count));
+        }
+        if let Some(expected) = &self.expected {
+            let categories: Vec<String> = expected
+                .iter()
+                .map(|(c, count)| format!("{} {}", c.replace('_', " "), dec(count.files as i128)))
+                .collect();
+            rows.push(
+                Row::new("", lgt, "Expected", self.expected_total())
+                    .with_note(categories.join(", ")),
+            );
+        }
+        let dirs = &self.dirs;
+        rows.push(Row::blank());
+        for (section, label, total, empty) in [
+            ("FOLDERS", SNP1, dirs.total_snap_1, &dirs.empty_snap_1),
+            ("", SNP2, dirs.total_snap_2, &dirs.empty_snap_2),
+        ] {
+            rows.push(Row::new(section, lgt, label, entries_without_size(total)).without_size());
+            if !empty.is_empty() {
+                rows.push(
+                    Row::sub(lgt, "Empty", entries_without_size(empty.len() as u64)).without_size(),
+                );
+            }
+        }
+        rows.extend([
+            Row::new(
+                "",
+                grn,
+                "Added",
+                entries_without_size(dirs.added.len() as u64),
+            )
+            .without_size(),
+            Row::new(
+                "",
+                red,
+                "Deleted",
+                entries_without_size(dirs.deleted.len() as u64),
+            )
+            .without_size(),
+        ]);
+        print_summary_table(&mut printer, rows);
+        if let Some(top) = &self.top {
+            let single = |f: &File| (f.size, f.path.display().to_string(), None);
+            print_file_lists(
+                &mut printer,
+                "LARGEST",
+                &[
+                    ("Added", grn, top.added.iter().map(single).collect()),
+                    ("Deleted", red, top.deleted.iter().map(single).collect()),
+                    (
+                        "Modified",
+                        ylw,
+                        top.modified
+                            .iter()
+                            .map(|(f1, f2)| {
+                                let delta = f2.size as i128 - f1.size as i128;
+                                let note = if delta == 0 {
+                                    "±0".to_string()
+                                } else {
+                                    dec_signed(delta)
+                                };
+                                return (f2.size, f2.path.display().to_string(), Some(note));
+                            })
+                            .collect(),
+                    ),
+                ],
+            );
+        }
+        let headers = CATEGORIES.map(|c| c.to_uppercase());
+        let columns: Vec<(&str, &str)> = headers
+            .iter()
+            .map(|c| c.as_str())
+            .zip([blu, blu, blu, grn, red, ylw])
+            .collect();
+        for (title, groups) in [
+            ("FOLDER", &self.dir_groups),
+            ("EXTENSION", &self.ext_groups),
+            ("TYPE", &self.type_groups),
+        ] {
+            if let Some(groups) = groups {
+                let rows: Vec<(String, Vec<Count>)> = groups
+                    .iter()
+                    .map(|g| (g.key.clone(), g.counts.to_vec()))
+                    .collect();
+                print_breakdown_table(&mut printer, title, &columns, &rows);
+            }
+        }
+        if let Some(h) = &self.histograms {
+            let labels = [
+                "Identical",
+                "Moved",
+                "Copied",
+                "Added",
+                "Deleted",
+                "Modified",
+            ];
+            let colours = [blu, blu, blu, grn, red, ylw];
+            let size_rows: Vec<(&str, &str, Vec<u64>)> = (0..CATEGORIES.len())
+                .map(|c| (labels[c], colours[c], h.size[c].map(|n| n.files).to_vec()))
+                .filter(|(_, _, counts)| counts.iter().any(|n| *n > 0))
+                .collect();
+            let age_rows: Vec<(&str, &str, Vec<u64>)> = (0..CATEGORIES.len())
+                .map(|c| (labels[c], colours[c], h.age[c].map(|n| n.files).to_vec()))
+                .filter(|(_, _, counts)| counts.iter().any(|n| *n > 0))
+                .collect();
+            let size_buckets = SIZE_BUCKETS.map(|(label, _)| label);
+            let age_buckets = AGE_BUCKETS.map(|(label, _)| label);
+            print_histogram_table(&mut printer, "SIZE", &size_buckets, &size_rows);
+            print_histogram_table(&mut printer, "AGE", &age_buckets, &age_rows);
+        }
+        if let Some(violations) = &self.policy_violations {
+            printer.print(policy_summary(violations, &printer.colours));
+        }
+        if self.suspicious.count.files > 0 {
+            printer.print(format!(
+                "\n{}Warning: {} file{} changed without a change of size or modification time. This might indicate data corruption.{}\n",
+                red,
+                dec(self.suspicious.count.files as i128),
+                if self.suspicious.count.files == 1 { "" } else { "s" },
+                printer.colours.reset
+            ));
+        }
+    }
+}
+
+// Lists the first few policy violations (all of them are listed in the
+// detailed report).
+fn policy_summary(violations: &[Violation], colours: &Colours) -> String {
+    const MAX_LINES: usize = 10;
+    let Colours {
+        red, green, reset, ..
+    } = colours;
+    if violations.is_empty() {
+        return format!("\n{green}Policy: no violations{reset}\n");
+    }
+    let mut out = format!(
+        "\n{red}Policy: {} violation{}{reset}\n",
+        dec(violations.len() as i128),
+        if violations.len() == 1 { "" } else { "s" }
+    );
+    for v in violations.iter().take(MAX_LINES) {
+        out.push_str(&format!("{red}  {}{reset}\n", v.describe()));
+    }
+    if violations.len() > MAX_LINES {
+        out.push_str(&format!(
+            "{red}  … and {} more{reset}\n",
+            dec((violations.len() - MAX_LINES) as i128)
         ));
     }
+    return out;
+}
+
+// A count of entries without size (e.g. folders or special files).
+fn entries_without_size(n: u64) -> Count {
+    let mut c = Count::new();
+    c.add(n, 0);
+    return c;
+}
+
+fn changed_xattrs(f1: &File, f2: &File) -> Vec<String> {
+    return f1
+        .xattrs
+        .as_ref()
+        .unwrap()
+        .diff(f2.xattrs.as_ref().unwrap())
+        .iter()
+        .map(|n| n.to_string_lossy().to_string())
+        .collect();
+}
+
+// The size difference between all files of snapshot 2 and snapshot 1.
+fn pair_delta(pairs: &PairStats) -> String {
+    let mut c1 = Count::new();
+    for (f1, _) in pairs.pairs() {
+        c1.add(1, f1.size);
+    }
+    return size_delta(&c1, &pairs.count);
+}
+
+fn size_delta(c1: &Count, c2: &Count) -> String {
+    let delta = c2.size as i128 - c1.size as i128;
+    if delta == 0 {
+        return "±0".to_string();
+    }
+    return dec_signed(delta);
 }
@@ -0,0 +1,124 @@
+use crate::error::Error;
+use std::path;
+
+// The parts of the directory tree that shall be compared. All paths
+// are relative to the snapshot roots; an empty path denotes the
+// entire tree.
+#[derive(Debug, Clone)]
+pub struct Scope {
+    paths: Vec<path::PathBuf>,
+}
+
+impl Scope {
+    pub fn whole() -> Scope {
+        return Scope {
+            paths: vec![path::PathBuf::new()],
+        };
+    }
+
+    pub fn new(paths: Vec<path::PathBuf>) -> Result<Scope, Error> {
+        let mut normalised = vec![];
+        for p in paths {
+            normalised.push(normalise(&p)?);
+        }
+
+        // Drop paths that are contained in other paths, so that no file
+        // is scheduled twice. After sorting, a path’s ancestors always
+        // precede it, and all its descendants follow directly.
+        normalised.sort();
+        let mut deduplicated: Vec<path::PathBuf> = vec![];
+        for p in normalised {
+            let is_contained = deduplicated
+                .last()
+                .map(|prev| p.starts_with(prev))
+                .unwrap_or(false);
+            if !is_contained {
+                deduplicated.push(p);
+            }
+        }
+        return Ok(Scope {
+            paths: deduplicated,
+        });
+    }
+
+    // Parses a list of paths, which are either separated by NUL
+    // characters or by newlines. Empty entries are ignored.
+    pub fn parse_list(text: &str) -> Vec<path::PathBuf> {
+        let separator = if text.contains('\0') { '\0' } else { '\n' };
+        return text
+            .split(separator)
+            .map(|l| l.strip_suffix('\r').unwrap_or(l))
+            .filter(|l| !l.is_empty())
+            .map(path::PathBuf::from)
+            .collect();
+    }
+
+    pub fn paths(&self) -> &Vec<path::PathBuf> {
+        return &self.paths;
+    }
+}
+
+fn normalise(p: &path::Path) -> Result<path::PathBuf, Error> {
+    let mut res = path::PathBuf::new();
+    for c in p.components() {
+        match c {
+            path::Component::Normal(name) => res.push(name),
+            path::Component::CurDir => {}
+            _ => {
+                return Err(Error::new(format!(
+                    "path must be relative to the snapshot root: {}",
+                    p.display()
+                )))
+            }
+        }
+    }
+    return Ok(res);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::scope::Scope;
+    use std::path::PathBuf;
+
+    #[test]
+    fn parse_list() {
+        assert_eq!(
+            Scope::parse_list("a/b.txt\nc\r\n\nd e/f\n"),
+            vec![
+                PathBuf::from("a/b.txt"),
+                PathBuf::from("c"),
+                PathBuf::from("d e/f")
+            ]
+        );
+        assert_eq!(
+            Scope::parse_list("a\nb\0c\0"),
+            vec![PathBuf::from("a\nb"), PathBuf::from("c")]
+        );
+    }
+
+    #[test]
+    fn normalises_and_deduplicates() {
+        let s = Scope::new(vec![
+            PathBuf::from("a-c"),
+            PathBuf::from("./a/b/x.txt"),
+            PathBuf::from("a/"),
+            PathBuf::from("d/./e"),
+            PathBuf::from("d/e"),
+        ])
+        .unwrap();
+        assert_eq!(
+            s.paths(),
+            &vec![
+                PathBuf::from("a"),
+                PathBuf::from("a-c"),
+                PathBuf::from("d/e")
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_paths_outside_root() {
+        assert!(Scope::new(vec![PathBuf::from("/etc")]).is_err());
+        assert!(Scope::new(vec![PathBuf::from("a/../../b")]).is_err());
+    }
+}
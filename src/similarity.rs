@@ -0,0 +1,254 @@
+use crate::checksum::{CheckSum, CheckSummer};
+use crate::file::File;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashMap};
+
+// The number of chunk hashes that are kept per file. The similarity of
+// two files is estimated from these (“bottom-k” MinHash).
+const SKETCH_SIZE: usize = 32;
+
+// Chunk boundaries are determined by the file contents (via a rolling
+// “gear” hash), so that insertions or deletions only affect the chunks
+// around them. The average chunk size is about 2 KB.
+const MIN_CHUNK: usize = 512;
+const MAX_CHUNK: usize = 16 * 1024;
+const BOUNDARY_MASK: u64 = ((1 << 11) - 1) << 53;
+
+// Chunk hashes that occur in more files than this (e.g. blocks of zeros)
+// are not used for finding candidates.
+const MAX_CANDIDATES_PER_CHUNK: usize = 1_000;
+
+const GEAR: [u64; 256] = gear_table();
+
+// Pseudo-random values (via SplitMix64), one for each byte value.
+const fn gear_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut x: u64 = 0;
+    let mut i = 0;
+    while i < 256 {
+        x = x.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = x;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    return table;
+}
+
+// A compact fingerprint of a file’s contents, which allows to estimate
+// how similar two files are.
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+pub struct Sketch(Box<[CheckSum]>);
+
+impl Sketch {
+    // The estimated share of common contents, in percent.
+    pub fn similarity(&self, other: &Sketch) -> u64 {
+        let (a, b) = (&self.0, &other.0);
+        let (mut i, mut j) = (0, 0);
+        let mut taken = 0;
+        let mut shared = 0;
+        while taken < SKETCH_SIZE && (i < a.len() || j < b.len()) {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                shared += 1;
+                i += 1;
+                j += 1;
+            } else if j == b.len() || (i < a.len() && a[i] < b[j]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+            taken += 1;
+        }
+        if taken == 0 {
+            return 0;
+        }
+        return shared * 100 / taken as u64;
+    }
+}
+
+// Computes a `Sketch` from data that is fed in chunk-wise.
+pub struct Sketcher {
+    gear: u64,
+    chunk_len: usize,
+    chunk: CheckSummer,
+    smallest: BTreeSet<CheckSum>,
+}
+
+impl Sketcher {
+    pub fn new() -> Sketcher {
+        return Sketcher {
+            gear: 0,
+            chunk_len: 0,
+            chunk: CheckSummer::new(),
+            smallest: BTreeSet::new(),
+        };
+    }
+
+    pub fn consume(&mut self, bs: &[u8]) -> &mut Self {
+        let mut start = 0;
+        for (i, b) in bs.iter().enumerate() {
+            self.gear = (self.gear << 1).wrapping_add(GEAR[*b as usize]);
+            self.chunk_len += 1;
+            if (self.chunk_len >= MIN_CHUNK && self.gear & BOUNDARY_MASK == 0)
+                || self.chunk_len >= MAX_CHUNK
+            {
+                self.chunk.consume(&bs[start..=i]);
+                start = i + 1;
+                self.end_chunk();
+            }
+        }
+        self.chunk.consume(&bs[start..]);
+        return self;
+    }
+
+    pub fn finalize(&mut self) -> Sketch {
+        if self.chunk_len > 0 {
+            self.end_chunk();
+        }
+        let hashes = std::mem::take(&mut self.smallest).into_iter().collect();
+        return Sketch(hashes);
+    }
+
+    fn end_chunk(&mut self) {
+        self.smallest.insert(self.chunk.finalize());
+        if self.smallest.len() > SKETCH_SIZE {
+            self.smallest.pop_last();
+        }
+        self.gear = 0;
+        self.chunk_len = 0;
+    }
+}
+
+// A file that was moved and modified at the same time.
+#[derive(Debug)]
+pub struct SimilarPair {
+    pub f1: File,
+    pub f2: File,
+    pub percent: u64,
+}
+
+// Pairs up deleted files with added files whose contents is at least
+// `threshold_percent` similar, most similar pairs first. Returns the
+// pairs, and the deleted and added files that couldn’t be paired up.
+pub fn pair_similar(
+    deleted: Vec<File>,
+    added: Vec<File>,
+    threshold_percent: u64,
+) -> (Vec<SimilarPair>, Vec<File>, Vec<File>) {
+    let mut index: HashMap<CheckSum, Vec<usize>> = HashMap::new();
+    for (i, f1) in deleted.iter().enumerate() {
+        for h in f1.sketch.iter().flat_map(|s| s.0.iter()) {
+            index.entry(*h).or_default().push(i);
+        }
+    }
+
+    let mut candidates = vec![];
+    for (j, f2) in added.iter().enumerate() {
+        let s2 = match &f2.sketch {
+            Some(s) => s,
+            None => continue,
+        };
+        let mut is: Vec<usize> =
+            s2.0.iter()
+                .filter_map(|h| index.get(h))
+                .filter(|is| is.len() <= MAX_CANDIDATES_PER_CHUNK)
+                .flatten()
+                .copied()
+                .collect();
+        is.sort();
+        is.dedup();
+        for i in is {
+            let percent = deleted[i].sketch.as_ref().unwrap().similarity(s2);
+            if percent >= threshold_percent {
+                let same_name = deleted[i].path.file_name() == f2.path.file_name();
+                candidates.push(((percent, same_name), i, j));
+            }
+        }
+    }
+    candidates.sort_by_key(|(score, i, j)| (Reverse(*score), *i, *j));
+
+    let mut deleted_slots: Vec<Option<File>> = deleted.into_iter().map(Some).collect();
+    let mut added_slots: Vec<Option<File>> = added.into_iter().map(Some).collect();
+    let mut pairs = vec![];
+    for ((percent, _), i, j) in candidates {
+        if deleted_slots[i].is_none() || added_slots[j].is_none() {
+            continue;
+        }
+        pairs.push(SimilarPair {
+            f1: deleted_slots[i].take().unwrap(),
+            f2: added_slots[j].take().unwrap(),
+            percent,
+        });
+    }
+    return (
+        pairs,
+        deleted_slots.into_iter().flatten().collect(),
+        added_slots.into_iter().flatten().collect(),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::similarity::{pair_similar, Sketcher};
+
+    // Pseudo-random text, so that chunk boundaries occur naturally.
+    fn text(seed: u64, len: usize) -> String {
+        let mut x = seed;
+        return (0..len)
+            .map(|_| {
+                x = x
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (b'a' + (x >> 59) as u8 % 26) as char
+            })
+            .collect();
+    }
+
+    fn sketched(path: &str, contents: &str) -> File {
+        let sketch = Sketcher::new().consume(contents.as_bytes()).finalize();
+        return File::from_strings(path, contents).with_sketch(sketch);
+    }
+
+    #[test]
+    fn estimates_similarity() {
+        let original = text(1, 100_000);
+        let edited = format!("{}INSERTED{}", &original[..50_000], &original[50_000..]);
+        let s1 = Sketcher::new().consume(original.as_bytes()).finalize();
+        let s2 = Sketcher::new().consume(edited.as_bytes()).finalize();
+        let s3 = Sketcher::new()
+            .consume(text(2, 100_000).as_bytes())
+            .finalize();
+        assert_eq!(s1.similarity(&s1), 100);
+        assert!(s1.similarity(&s2) >= 80);
+        assert_eq!(s1.similarity(&s3), 0);
+
+        // The result doesn’t depend on how the data is fed in.
+        let mut sketcher = Sketcher::new();
+        for c in original.as_bytes().chunks(777) {
+            sketcher.consume(c);
+        }
+        assert_eq!(sketcher.finalize(), s1);
+    }
+
+    #[test]
+    fn pairs_similar_files() {
+        let a = text(1, 50_000);
+        let b = text(2, 50_000);
+        let (pairs, deleted, added) = pair_similar(
+            vec![sketched("a.txt", &a), sketched("b.txt", &b)],
+            vec![
+                sketched("new/a.txt", &format!("{}!", a)),
+                sketched("c.txt", &text(3, 50_000)),
+            ],
+            50,
+        );
+        assert_eq!(pairs.len(), 1);
+        assert_eq!(pairs[0].f1.path.to_str(), Some("a.txt"));
+        assert_eq!(pairs[0].f2.path.to_str(), Some("new/a.txt"));
+        assert!(pairs[0].percent >= 90);
+        assert_eq!(deleted[0].path.to_str(), Some("b.txt"));
+        assert_eq!(added[0].path.to_str(), Some("c.txt"));
+    }
+}
@@ -1,13 +1,16 @@
-use crate::checksum::CheckSummer;
+use crate::checksum::{BlockSummer, CheckSum, CheckSummer};
 use crate::cli::CtrlCSignal;
 use crate::dir_iter::DirIterator;
 use crate::printer::TerminalPrinter;
 use crate::progress::Progress;
+use crate::similarity::{Sketch, Sketcher};
 use crate::snapshot::Snapshot;
-use crate::{file, Error};
-use file::File;
-use std::io::BufRead;
+use crate::xattr::Xattrs;
+use crate::{file, file_type, Error};
+use file::{File, Kind};
+use std::io::{BufRead, Read};
 use std::ops::DerefMut;
+use std::os::unix::fs::MetadataExt;
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::{fs, io, path, thread};
@@ -17,13 +20,43 @@ pub const CHUNK_SIZE: u64 = 1024 * 1024 * 10; // ~10MB
 pub struct Snapper {
     num_workers: usize,
     ctrlc_signal: CtrlCSignal,
+    capture: Capture,
+}
+
+// Which information shall be captured for every file, besides its checksum.
+#[derive(Debug, Copy, Clone)]
+pub struct Capture {
+    // A similarity sketch (for regular files only).
+    pub sketches: bool,
+    // The extended attributes.
+    pub xattrs: bool,
+    // Whether the extended attributes of symlink targets are read, instead
+    // of the ones of the symlinks themselves.
+    pub follow_symlinks: bool,
+    // The file type, as detected from the leading bytes.
+    pub file_types: bool,
+    // The checksums of the blocks of the given size (for regular files only).
+    pub block_size: Option<file::SizeBytes>,
+}
+
+impl Capture {
+    pub fn new() -> Capture {
+        return Capture {
+            sketches: false,
+            xattrs: false,
+            follow_symlinks: false,
+            file_types: false,
+            block_size: None,
+        };
+    }
 }
 
 impl Snapper {
-    pub fn new(num_workers: usize, ctrlc_signal: CtrlCSignal) -> Snapper {
+    pub fn new(num_workers: usize, ctrlc_signal: CtrlCSignal, capture: Capture) -> Snapper {
         return Snapper {
             num_workers,
             ctrlc_signal,
+            capture,
         };
     }
 
@@ -31,13 +64,16 @@ impl Snapper {
     // The file hashes are computed in parallel. Progress is updated continuously.
     pub fn process<S>(
         &self,
-        dir_it: DirIterator,
-        snap: S,
+        mut dir_it: DirIterator,
+        mut snap: S,
         progress: Progress<TerminalPrinter>,
     ) -> Result<S, Error>
     where
         S: Snapshot + std::fmt::Debug + Send + 'static,
     {
+        for d in dir_it.take_dirs() {
+            snap.add_dir(d);
+        }
         let dir_it_arc = Arc::new(Mutex::new(dir_it));
         let snap_arc = Arc::new(Mutex::new(snap));
         let progress_arc = Arc::new(Mutex::new(progress));
@@ -49,6 +85,7 @@ impl Snapper {
                 Arc::clone(&snap_arc),
                 Arc::clone(&progress_arc),
                 self.ctrlc_signal.clone(),
+                self.capture,
             );
             handles.push(handle);
         }
@@ -72,6 +109,7 @@ fn spawn_worker<S>(
     snap_mtx: Arc<Mutex<S>>,
     progress_mtx: Arc<Mutex<Progress<TerminalPrinter>>>,
     ctrl_c: CtrlCSignal,
+    capture: Capture,
 ) -> JoinHandle<Result<(), Error>>
 where
     S: Snapshot + std::fmt::Debug + Send + 'static,
@@ -82,7 +120,7 @@ where
             p.process_inc(0, 0 as file::SizeBytes);
         }
         loop {
-            let (p, root) = {
+            let ((p, kind, meta), root) = {
                 let (entry, root) = {
                     let mut di_mtx = dir_it_mtx.lock().unwrap();
                     let di = di_mtx.deref_mut();
@@ -94,38 +132,52 @@ where
                 (entry.unwrap(), root)
             };
 
-            let disk_file = open_file(&p).map_err(|e| {
-                return Error::from(format!("cannot open file: {}", p.display()), e.to_string());
-            })?;
-            let mut reader = io::BufReader::with_capacity(CHUNK_SIZE as usize, disk_file);
-            let mut size_bytes: file::SizeBytes = 0;
-            let mut checksummer = CheckSummer::new();
-            loop {
-                if ctrl_c.has_triggered() {
-                    println!();
-                    std::process::exit(255);
-                }
-                let buffer = reader.fill_buf().map_err(|e| {
-                    return Error::from(
-                        format!("failed to read from file: {}", p.display()),
-                        e.to_string(),
-                    );
-                })?;
-                let length = buffer.len();
-                if length == 0 {
-                    break;
+            let rel_path = p.strip_prefix(&root).unwrap().to_path_buf();
+            let r = match kind {
+                Kind::Regular => {
+                    let prefix_length = {
+                        let s = snap_mtx.lock().unwrap();
+                        s.prefix_length(&rel_path)
+                    };
+                    read_file(&p, &progress_mtx, &ctrl_c, capture, prefix_length)?
                 }
-                checksummer.consume(&buffer);
-                size_bytes += length as file::SizeBytes;
-                reader.consume(length);
+                Kind::Symlink => read_symlink(&p)?,
+                _ => read_special(&p, kind)?,
+            };
+
+            let mut f = File::new(rel_path, kind, r.size_bytes, r.check_sum).with_meta(meta);
+            if let Some(s) = r.sketch {
+                f = f.with_sketch(s);
+            }
+            if let Some(c) = r.prefix_check_sum {
+                f = f.with_prefix_check_sum(c);
+            }
+            if let Some(t) = r.file_type {
+                f = f.with_file_type(t);
+            }
+            if let Some(b) = r.block_check_sums {
+                f = f.with_block_check_sums(b);
+            }
+            if kind == Kind::Regular {
+                let source = {
+                    let s = snap_mtx.lock().unwrap();
+                    s.truncation_source(&f.path, r.size_bytes)
+                };
+                // If the source cannot be read, the file isn’t recognised as
+                // truncated, but it’s still reported as modified.
+                if let Some(c) = source.and_then(|p1| read_prefix(&p1, r.size_bytes, &ctrl_c).ok())
                 {
-                    let mut p = progress_mtx.lock().unwrap();
-                    p.process_inc(0, length as file::SizeBytes);
+                    f = f.with_source_prefix_check_sum(c);
+                }
+            }
+            if capture.xattrs {
+                // Files whose extended attributes cannot be read are compared
+                // without them.
+                match Xattrs::read(&p, capture.follow_symlinks) {
+                    Ok(xattrs) => f = f.with_xattrs(xattrs),
+                    Err(_) => progress_mtx.lock().unwrap().process_skip_xattrs(),
                 }
             }
-
-            let rel_path = p.strip_prefix(&root).unwrap().to_path_buf();
-            let f = File::new(rel_path, size_bytes, checksummer.finalize());
 
             {
                 let mut s = snap_mtx.lock().unwrap();
@@ -140,6 +192,233 @@ where
     });
 }
 
+struct ReadResult {
+    size_bytes: file::SizeBytes,
+    check_sum: CheckSum,
+    sketch: Option<Sketch>,
+    prefix_check_sum: Option<CheckSum>,
+    file_type: Option<&'static str>,
+    block_check_sums: Option<Vec<CheckSum>>,
+}
+
+// Computes the checksum of the file contents, by reading the file chunk-wise.
+// Whatever else `capture` requests (the similarity sketch, the file type and
+// the block checksums) is derived from the same chunks, as is the checksum of
+// the leading `prefix_length` bytes, if given.
+fn read_file(
+    p: &path::Path,
+    progress_mtx: &Mutex<Progress<TerminalPrinter>>,
+    ctrl_c: &CtrlCSignal,
+    capture: Capture,
+    prefix_length: Option<file::SizeBytes>,
+) -> Result<ReadResult, Error> {
+    let disk_file = open_file(p).map_err(|e| {
+        return Error::from(format!("cannot open file: {}", p.display()), e.to_string());
+    })?;
+    let mut reader = io::BufReader::with_capacity(CHUNK_SIZE as usize, disk_file);
+    let mut size_bytes: file::SizeBytes = 0;
+    let mut checksummer = CheckSummer::new();
+    let mut prefix_check_sum = None;
+    let mut file_type = None;
+    let mut sketcher = if capture.sketches {
+        Some(Sketcher::new())
+    } else {
+        None
+    };
+    let mut block_summer = capture.block_size.map(BlockSummer::new);
+    loop {
+        if ctrl_c.has_triggered() {
+            println!();
+            std::process::exit(255);
+        }
+        let buffer = reader.fill_buf().map_err(|e| {
+            return Error::from(
+                format!("failed to read from file: {}", p.display()),
+                e.to_string(),
+            );
+        })?;
+        let length = buffer.len();
+        if length == 0 {
+            break;
+        }
+        match prefix_length {
+            Some(l) if size_bytes < l && l <= size_bytes + length as file::SizeBytes => {
+                let (head, tail) = buffer.split_at((l - size_bytes) as usize);
+                prefix_check_sum = Some(checksummer.consume(head).peek());
+                checksummer.consume(tail);
+            }
+            _ => {
+                checksummer.consume(buffer);
+            }
+        }
+        if let Some(s) = &mut sketcher {
+            s.consume(buffer);
+        }
+        if let Some(b) = &mut block_summer {
+            b.consume(buffer);
+        }
+        if capture.file_types && size_bytes == 0 {
+            file_type = file_type::detect(buffer);
+        }
+        size_bytes += length as file::SizeBytes;
+        reader.consume(length);
+        {
+            let mut p = progress_mtx.lock().unwrap();
+            p.process_inc(0, length as file::SizeBytes);
+        }
+    }
+    return Ok(ReadResult {
+        size_bytes,
+        check_sum: checksummer.finalize(),
+        sketch: sketcher.map(|mut s| s.finalize()),
+        prefix_check_sum,
+        file_type,
+        block_check_sums: block_summer.map(|b| b.finalize()),
+    });
+}
+
+// Computes the checksum of the symlink target path (without following the
+// symlink). Symlinks don’t contribute to the byte count.
+fn read_symlink(p: &path::Path) -> Result<ReadResult, Error> {
+    let target = fs::read_link(p).map_err(|e| {
+        return Error::from(
+            format!("cannot read symlink: {}", p.display()),
+            e.to_string(),
+        );
+    })?;
+    let check_sum = CheckSummer::new()
+        .consume(target.as_os_str().as_encoded_bytes())
+        .finalize();
+    return Ok(ReadResult {
+        size_bytes: 0,
+        check_sum,
+        sketch: None,
+        prefix_check_sum: None,
+        file_type: None,
+        block_check_sums: None,
+    });
+}
+
+// Computes a checksum for a special file, based on its kind and (for
+// device nodes) its device number. Special files are never opened.
+fn read_special(p: &path::Path, kind: Kind) -> Result<ReadResult, Error> {
+    let m = fs::metadata(p).map_err(|e| {
+        return Error::from(
+            format!("cannot inspect file: {}", p.display()),
+            e.to_string(),
+        );
+    })?;
+    let check_sum = CheckSummer::new()
+        .consume(kind.name().as_bytes())
+        .consume(&m.rdev().to_le_bytes())
+        .finalize();
+    return Ok(ReadResult {
+        size_bytes: 0,
+        check_sum,
+        sketch: None,
+        prefix_check_sum: None,
+        file_type: None,
+        block_check_sums: None,
+    });
+}
+
+// Computes the checksum of the first `length` bytes of a file.
+fn read_prefix(
+    p: &path::Path,
+    length: file::SizeBytes,
+    ctrl_c: &CtrlCSignal,
+) -> io::Result<CheckSum> {
+    let mut reader = io::BufReader::with_capacity(CHUNK_SIZE as usize, open_file(p)?).take(length);
+    let mut checksummer = CheckSummer::new();
+    let mut read: file::SizeBytes = 0;
+    loop {
+        if ctrl_c.has_triggered() {
+            println!();
+            std::process::exit(255);
+        }
+        let buffer = reader.fill_buf()?;
+        let n = buffer.len();
+        if n == 0 {
+            break;
+        }
+        checksummer.consume(buffer);
+        read += n as file::SizeBytes;
+        reader.consume(n);
+    }
+    if read < length {
+        return Err(io::Error::from(io::ErrorKind::UnexpectedEof));
+    }
+    return Ok(checksummer.finalize());
+}
+
 pub fn open_file(p: &path::Path) -> io::Result<fs::File> {
-    return fs::File::options().read(true).open(&p);
+    return fs::File::options().read(true).open(p);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cli::CtrlCSignal;
+    use crate::dir_iter::DirIterator;
+    use crate::filter::{Filter, SymlinkPolicy};
+    use crate::printer::TerminalPrinter;
+    use crate::progress::Progress;
+    use crate::report::Report;
+    use crate::scope::Scope;
+    use crate::snapper::{Capture, Snapper};
+    use crate::snapshot_1::Snapshot1;
+    use crate::snapshot_2::{Options, Snapshot2};
+    use std::fs;
+    use std::path::Path;
+
+    // Runs a full comparison of two folders on disk.
+    fn compare(root_1: &Path, root_2: &Path, mut options: Options, capture: Capture) -> Report {
+        let snapper = Snapper::new(2, CtrlCSignal::new(), capture);
+        let filters = Filter::new(SymlinkPolicy::Skip, false);
+        let mut progress = Progress::new(TerminalPrinter::new_plain(), "Snap 1", None);
+        let dir_it = DirIterator::scan(2, root_1, &Scope::whole(), filters, &mut progress).unwrap();
+        let snap_1 = snapper.process(dir_it, Snapshot1::new(), progress).unwrap();
+
+        let mut progress = Progress::new(TerminalPrinter::new_plain(), "Snap 2", None);
+        let dir_it = DirIterator::scan(2, root_2, &Scope::whole(), filters, &mut progress).unwrap();
+        options.snap_1_root = Some(root_1.to_path_buf());
+        let snap_2 = Snapshot2::new_with_options(snap_1, options);
+        return snapper
+            .process(dir_it, snap_2, progress)
+            .unwrap()
+            .conclude();
+    }
+
+    #[test]
+    fn detects_truncated_files() {
+        let (root_1, root_2) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::write(root_1.path().join("truncated"), "hello world").unwrap();
+        fs::write(root_2.path().join("truncated"), "hello").unwrap();
+        fs::write(root_1.path().join("shrunk"), "hello world").unwrap();
+        fs::write(root_2.path().join("shrunk"), "world").unwrap();
+
+        let res = compare(root_1.path(), root_2.path(), Options::new(), Capture::new());
+        assert_eq!(res.modified_snap_2.count.files, 2);
+        assert_eq!(res.truncated.count.files, 1);
+        let (f1, f2) = &res.truncated.pairs()[0];
+        assert_eq!(f1.path, Path::new("truncated"));
+        assert_eq!(f2.size, 5);
+    }
+
+    #[test]
+    fn estimates_changed_bytes() {
+        let (root_1, root_2) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::write(root_1.path().join("modified"), "aaaabbbbcccc").unwrap();
+        fs::write(root_2.path().join("modified"), "aaaaXbbbccccdd").unwrap();
+        fs::write(root_1.path().join("appended"), "aaaa").unwrap();
+        fs::write(root_2.path().join("appended"), "aaaab").unwrap();
+
+        let mut options = Options::new();
+        options.block_size = Some(4);
+        let mut capture = Capture::new();
+        capture.block_size = Some(4);
+        let res = compare(root_1.path(), root_2.path(), options, capture);
+        assert_eq!(res.changed_bytes.get(Path::new("modified")), Some(&6));
+        assert_eq!(res.changed_bytes_total, Some(7));
+        assert_eq!(res.changed_bytes_skipped, 0);
+    }
 }
@@ -1,15 +1,32 @@
 use crate::checksum::CheckSum;
-use crate::file::File;
+use crate::file::{Dir, File, Kind, SizeBytes};
 use std::collections::hash_map::Drain;
 use std::collections::HashMap;
+use std::path;
 
 pub trait Snapshot {
     fn add(&mut self, f1: File);
+
+    fn add_dir(&mut self, d: Dir);
+
+    // The number of leading bytes of a file whose checksum shall be
+    // computed separately (see `File::prefix_check_sum`), if any.
+    fn prefix_length(&self, _p: &path::Path) -> Option<SizeBytes> {
+        return None;
+    }
+
+    // The file whose leading bytes (up to the given size) shall be checksummed
+    // for comparison with a file (see `File::source_prefix_check_sum`), if any.
+    fn truncation_source(&self, _p: &path::Path, _size: SizeBytes) -> Option<path::PathBuf> {
+        return None;
+    }
 }
 
+// Files grouped by their kind and checksum, since the checksum of a symlink
+// (of its target) could otherwise coincide with that of a regular file.
 #[derive(Debug)]
 pub struct FilesByChecksums {
-    map: HashMap<CheckSum, Vec<File>>,
+    map: HashMap<(Kind, CheckSum), Vec<File>>,
 }
 
 impl FilesByChecksums {
@@ -20,35 +37,31 @@ impl FilesByChecksums {
     }
 
     pub fn add(&mut self, f: File) {
-        if !self.map.contains_key(&f.check_sum) {
-            self.map.insert(f.check_sum, vec![]);
-        }
-        self.map.get_mut(&f.check_sum).unwrap().push(f);
+        self.map.entry((f.kind, f.check_sum)).or_default().push(f);
     }
 
-    pub fn withdraw(&mut self, checksum: &CheckSum) -> Option<File> {
-        if !self.map.contains_key(checksum) {
-            return None;
-        }
-        let f = self.map.get_mut(checksum).unwrap().remove(0);
-        if self.map.get(checksum).unwrap().is_empty() {
-            self.map.remove(checksum);
-        }
-        return Some(f);
+    // Removes all files with the given kind and checksum.
+    pub fn withdraw_all(&mut self, kind: Kind, checksum: CheckSum) -> Vec<File> {
+        return self.map.remove(&(kind, checksum)).unwrap_or_default();
     }
 
-    pub fn drain(&mut self) -> Drain<'_, CheckSum, Vec<File>> {
+    pub fn drain(&mut self) -> Drain<'_, (Kind, CheckSum), Vec<File>> {
         return self.map.drain();
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::file::File;
+    use crate::checksum::BlockSummer;
+    use crate::file::{Dir, File};
+    use crate::meta::Meta;
+    use crate::path_key::{Mapping, PathKeys, UnicodeForm};
     use crate::snapshot::Snapshot;
+    use crate::top::TopBy;
     use crate::{snapshot_1, snapshot_2};
     use snapshot_1::Snapshot1;
     use snapshot_2::Snapshot2;
+    use std::path::{Path, PathBuf};
 
     #[test]
     fn test_identical_files() {
@@ -104,6 +117,21 @@ mod tests {
         assert_eq!(res.total_snap_2.count.files, 2);
     }
 
+    #[test]
+    fn test_files_with_same_contents() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("/original", "same"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("/moved", "same"));
+        s2.add(File::from_strings("/duplicate", "same"));
+
+        let res = s2.conclude();
+        assert_eq!(res.moved.count.files, 1);
+        assert_eq!(res.copied.count.files, 1);
+        assert_eq!(res.deleted.count.files, 0);
+    }
+
     #[test]
     fn test_added_files() {
         let s1 = Snapshot1::new();
@@ -172,4 +200,394 @@ mod tests {
         assert_eq!(res.moved.count.files, 1);
         assert_eq!(res.identical.count.files, 1);
     }
+
+    #[test]
+    fn test_copied_files() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("/identical", "1"));
+        s1.add(File::from_strings("/moved", "2"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("/identical", "1"));
+        s2.add(File::from_strings("/identical-copy", "1"));
+        s2.add(File::from_strings("/moved-1", "2"));
+        s2.add(File::from_strings("/moved-2", "2"));
+        s2.add(File::from_strings("/added", "3"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 1);
+        assert_eq!(res.moved.count.files, 1);
+        assert_eq!(res.copied.count.files, 2);
+        assert_eq!(res.added.count.files, 1);
+        assert_eq!(res.total_snap_2.count.files, 5);
+    }
+
+    #[test]
+    fn test_moves_with_same_contents() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("a/logo.png", "logo"));
+        s1.add(File::from_strings("z/other/logo.png", "logo"));
+        s1.add(File::from_strings("z/logo-old.png", "logo"));
+
+        let mut s2 = Snapshot2::new_with_options(
+            s1,
+            snapshot_2::Options {
+                dir_move_threshold: None,
+                ..snapshot_2::Options::new()
+            },
+        );
+        s2.add(File::from_strings("z/other/img/logo.png", "logo"));
+        s2.add(File::from_strings("a/img/logo.png", "logo"));
+
+        let res = s2.conclude();
+        let mut moves: Vec<_> = res
+            .moved
+            .pairs()
+            .iter()
+            .map(|(f1, f2)| (f1.path.to_str().unwrap(), f2.path.to_str().unwrap()))
+            .collect();
+        moves.sort();
+        assert_eq!(
+            moves,
+            vec![
+                ("a/logo.png", "a/img/logo.png"),
+                ("z/other/logo.png", "z/other/img/logo.png"),
+            ]
+        );
+        assert_eq!(res.deleted.count.files, 1);
+    }
+
+    #[test]
+    fn test_appended_files() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("/app.log", "line 1\n"));
+        s1.add(File::from_strings("/rewritten.log", "line 1\n"));
+        s1.add(File::from_strings("/shrunk.log", "line 1\nline 2\n"));
+
+        let mut s2 = Snapshot2::new(s1);
+        assert_eq!(s2.prefix_length(Path::new("/app.log")), Some(7));
+        assert_eq!(s2.prefix_length(Path::new("/unknown.log")), None);
+        s2.add(
+            File::from_strings("/app.log", "line 1\nline 2\n")
+                .with_prefix_check_sum(File::from_strings("", "line 1\n").check_sum),
+        );
+        s2.add(
+            File::from_strings("/rewritten.log", "line 2\nline 3\n")
+                .with_prefix_check_sum(File::from_strings("", "line 2\n").check_sum),
+        );
+        // Without access to snapshot 1, truncation cannot be verified.
+        s2.add(File::from_strings("/shrunk.log", "line 1\n"));
+
+        let res = s2.conclude();
+        assert_eq!(res.appended.count.files, 1);
+        assert_eq!(res.appended.pairs()[0].1.path.to_str(), Some("/app.log"));
+        assert_eq!(res.truncated.count.files, 0);
+        assert_eq!(res.modified_snap_2.count.files, 3);
+        assert_eq!(res.modified_snap_2.files().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_block_changes() {
+        let blocks = |contents: &str| {
+            let mut b = BlockSummer::new(2);
+            b.consume(contents.as_bytes());
+            return b.finalize();
+        };
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("/app.log", "line 1\n"));
+        s1.add(File::from_strings("/data.bin", "1234").with_block_check_sums(blocks("1234")));
+        s1.add(File::from_strings("/other.bin", "1234"));
+
+        let mut s2 = Snapshot2::new_with_options(
+            s1,
+            snapshot_2::Options {
+                block_size: Some(2),
+                ..snapshot_2::Options::new()
+            },
+        );
+        s2.add(
+            File::from_strings("/app.log", "line 1\nline 2\n")
+                .with_prefix_check_sum(File::from_strings("", "line 1\n").check_sum),
+        );
+        s2.add(File::from_strings("/data.bin", "12x45").with_block_check_sums(blocks("12x45")));
+        // Without block checksums, the file cannot be compared.
+        s2.add(File::from_strings("/other.bin", "12345"));
+
+        let res = s2.conclude();
+        assert_eq!(res.changed_bytes.get(Path::new("/data.bin")), Some(&3));
+        assert_eq!(res.changed_bytes.get(Path::new("/other.bin")), None);
+        assert_eq!(res.changed_bytes_skipped, 1);
+        assert_eq!(res.changed_bytes_total, Some(10));
+    }
+
+    #[test]
+    fn test_modified_files_storage() {
+        let compare = |options: snapshot_2::Options| {
+            let mut s1 = Snapshot1::new();
+            s1.add(File::from_strings("/a", "1"));
+            let mut s2 = Snapshot2::new_with_options(s1, options);
+            s2.add(File::from_strings("/a", "2"));
+            return s2.conclude();
+        };
+        let res = compare(snapshot_2::Options::new());
+        assert!(res.modified_snap_1.files().is_none());
+
+        let res = compare(snapshot_2::Options {
+            top: Some((1, TopBy::Bytes)),
+            ..snapshot_2::Options::new()
+        });
+        assert_eq!(res.modified_snap_1.files().map(|fs| fs.len()), Some(1));
+    }
+
+    #[test]
+    fn test_dirs() {
+        let mut s1 = Snapshot1::new();
+        s1.add_dir(Dir::new(PathBuf::from("a"), false));
+        s1.add_dir(Dir::new(PathBuf::from("a/empty"), true));
+        s1.add_dir(Dir::new(PathBuf::from("b"), true));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add_dir(Dir::new(PathBuf::from("a"), false));
+        s2.add_dir(Dir::new(PathBuf::from("c"), true));
+
+        let res = s2.conclude();
+        assert_eq!(res.dirs.total_snap_1, 3);
+        assert_eq!(res.dirs.total_snap_2, 2);
+        assert_eq!(
+            res.dirs.deleted,
+            vec![PathBuf::from("a/empty"), PathBuf::from("b")]
+        );
+        assert_eq!(res.dirs.added, vec![PathBuf::from("c")]);
+        assert_eq!(res.dirs.empty_snap_1.len(), 2);
+        assert_eq!(res.dirs.empty_snap_2, vec![PathBuf::from("c")]);
+    }
+
+    #[test]
+    fn test_type_changes() {
+        let mut s1 = Snapshot1::new();
+        s1.add_dir(Dir::new(PathBuf::from("became_file"), true));
+        s1.add(File::from_strings("became_dir", "x"));
+        s1.add(File::from_strings("became_link", "abc"));
+        s1.add(File::from_strings("same", "abc"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add_dir(Dir::new(PathBuf::from("became_dir"), true));
+        s2.add(File::from_strings("became_file", "yz"));
+        s2.add(File::symlink_from_strings("became_link", "abc"));
+        s2.add(File::from_strings("same", "abc"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 1);
+        assert_eq!(res.type_changed.count.files, 3);
+        assert_eq!(res.type_changed.count.size, 2);
+        let mut changes: Vec<(&str, &str)> = res
+            .type_changed
+            .changes()
+            .iter()
+            .map(|(from, to, _, _)| (*from, *to))
+            .collect();
+        changes.sort();
+        assert_eq!(
+            changes,
+            vec![("dir", "file"), ("file", "dir"), ("file", "symlink")]
+        );
+        assert_eq!(res.added.count.files, 0);
+        assert_eq!(res.deleted.count.files, 0);
+        assert_eq!(res.dirs.added.len() + res.dirs.deleted.len(), 0);
+    }
+
+    #[test]
+    fn test_suspicious_files() {
+        let meta = |mtime, mtime_nsec| Meta {
+            mode: 0o644,
+            uid: 0,
+            gid: 0,
+            mtime,
+            mtime_nsec,
+        };
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("/rotten", "abc").with_meta(meta(1, 5)));
+        s1.add(File::from_strings("/edited", "abc").with_meta(meta(1, 5)));
+        s1.add(File::from_strings("/retouched", "abc").with_meta(meta(1, 5)));
+        s1.add(File::from_strings("/grown", "abc").with_meta(meta(1, 5)));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("/rotten", "abd").with_meta(meta(1, 5)));
+        s2.add(File::from_strings("/edited", "abd").with_meta(meta(2, 5)));
+        // Edited within the same second.
+        s2.add(File::from_strings("/retouched", "abd").with_meta(meta(1, 7)));
+        s2.add(File::from_strings("/grown", "abcd").with_meta(meta(1, 5)));
+
+        let res = s2.conclude();
+        assert_eq!(res.modified_snap_2.count.files, 4);
+        assert_eq!(res.suspicious.count.files, 1);
+        assert_eq!(
+            res.suspicious.files().unwrap()[0].path,
+            PathBuf::from("/rotten")
+        );
+    }
+
+    #[test]
+    fn test_empty_files() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("a/.keep", ""));
+        s1.add(File::from_strings("a/x.txt", "x"));
+        s1.add(File::from_strings("b/y.txt", "yy"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("b/__init__.py", ""));
+        s2.add(File::from_strings("c/x.txt", "x"));
+        s2.add(File::from_strings("c/y.txt", "yy"));
+
+        let res = s2.conclude();
+        assert_eq!(res.moved.count.files, 2);
+        assert_eq!(res.added.count.files, 1);
+        assert_eq!(res.added_empty.files, 1);
+        assert_eq!(res.deleted.count.files, 1);
+        assert_eq!(res.deleted_empty.files, 1);
+
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("a/x.txt", "x"));
+        s1.add(File::from_strings("b/y.txt", "yy"));
+
+        let mut s2 = Snapshot2::new_with_options(
+            s1,
+            snapshot_2::Options {
+                min_move_size: 2,
+                ..snapshot_2::Options::new()
+            },
+        );
+        s2.add(File::from_strings("a/x.txt", "x"));
+        s2.add(File::from_strings("a/x-copy.txt", "x"));
+        s2.add(File::from_strings("c/y.txt", "yy"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 1);
+        assert_eq!(res.copied.count.files, 0);
+        assert_eq!(res.added.count.files, 1);
+        assert_eq!(res.moved.count.files, 1);
+    }
+
+    #[test]
+    fn test_dir_moves() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("old/1", "1"));
+        s1.add(File::from_strings("old/sub/2", "2"));
+        s1.add(File::from_strings("other/3", "3"));
+        s1.add(File::from_strings("other/4", "4"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("new/1", "1"));
+        s2.add(File::from_strings("new/sub/2", "2"));
+        s2.add(File::from_strings("other-new/3", "3"));
+        s2.add(File::from_strings("other/4", "4"));
+
+        let res = s2.conclude();
+        assert_eq!(res.moved.count.files, 3);
+        assert_eq!(res.dir_moves.len(), 1);
+        assert_eq!(res.dir_moves[0].count.files, 2);
+        assert_eq!(res.moved.pairs().len(), 1);
+        assert_eq!(res.identical.count.files, 1);
+    }
+
+    #[test]
+    fn test_mapped_paths() {
+        let keys = PathKeys::new(
+            vec![Mapping::parse_prefix("/old=/new").unwrap()],
+            None,
+            false,
+        );
+        let mut s1 = Snapshot1::new_with_keys(keys);
+        s1.add(File::from_strings("/old/identical", "identical"));
+        s1.add(File::from_strings("/old/modified", "modified"));
+        s1.add(File::from_strings("/old/collision", "mapped"));
+        s1.add(File::from_strings("/new/collision", "genuine"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("/new/identical", "identical"));
+        s2.add(File::from_strings("/new/modified", "MODIFIED"));
+        s2.add(File::from_strings("/new/collision", "genuine"));
+        s2.add(File::from_strings("/old/collision", "mapped"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 3);
+        assert_eq!(res.modified_snap_2.count.files, 1);
+        assert_eq!(res.moved.count.files, 0);
+        assert_eq!(res.added.count.files, 0);
+        assert_eq!(res.deleted.count.files, 0);
+    }
+
+    #[test]
+    fn test_respelled_paths() {
+        let keys = PathKeys::new(vec![], Some(UnicodeForm::Nfc), true);
+        let mut s1 = Snapshot1::new_with_keys(keys);
+        s1.add(File::from_strings("/Cafe\u{301}.txt", "respelled"));
+        s1.add(File::from_strings("/README", "modified"));
+        s1.add(File::from_strings("/exact", "exact-1"));
+        s1.add(File::from_strings("/EXACT", "exact-2"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::from_strings("/caf\u{e9}.txt", "respelled"));
+        s2.add(File::from_strings("/readme", "MODIFIED"));
+        s2.add(File::from_strings("/EXACT", "exact-2"));
+        s2.add(File::from_strings("/exact", "exact-1"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 2);
+        assert_eq!(res.moved.count.files, 1);
+        assert_eq!(res.respelled.count.files, 1);
+        assert_eq!(
+            res.respelled.pairs()[0].1.path.to_str(),
+            Some("/caf\u{e9}.txt")
+        );
+        assert_eq!(res.modified_snap_2.count.files, 1);
+        assert_eq!(res.added.count.files, 0);
+        assert_eq!(res.deleted.count.files, 0);
+    }
+
+    #[test]
+    fn test_symlinks() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::symlink_from_strings("/link-identical", "a.txt"));
+        s1.add(File::symlink_from_strings("/link-modified", "b.txt"));
+        s1.add(File::from_strings("/file-to-link", "c.txt"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::symlink_from_strings("/link-identical", "a.txt"));
+        s2.add(File::symlink_from_strings("/link-modified", "bbb.txt"));
+        s2.add(File::symlink_from_strings("/file-to-link", "c.txt"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 1);
+        assert_eq!(res.modified_snap_2.count.files, 1);
+        assert_eq!(res.type_changed.count.files, 1);
+        assert_eq!(res.total_snap_1.count.size, 5);
+        assert_eq!(res.total_snap_2.count.size, 0);
+    }
+
+    #[test]
+    fn test_symlinks_and_files_with_same_contents() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::symlink_from_strings("/link", "c.txt"));
+        s1.add(File::symlink_from_strings("/link-to-file", "d.txt"));
+        s1.add(File::symlink_from_strings("/old-link", "e.txt"));
+
+        let mut s2 = Snapshot2::new(s1);
+        s2.add(File::symlink_from_strings("/link", "c.txt"));
+        s2.add(File::from_strings("/copy", "c.txt"));
+        s2.add(File::from_strings("/file", "d.txt"));
+        s2.add(File::symlink_from_strings("/new-link", "e.txt"));
+
+        let res = s2.conclude();
+        assert_eq!(res.identical.count.files, 1);
+        assert_eq!(res.copied.count.files, 0);
+        assert_eq!(res.moved.count.files, 1);
+        assert_eq!(res.moved.pairs()[0].1.path, PathBuf::from("/new-link"));
+        assert_eq!(res.added.count.files, 2);
+        assert_eq!(res.deleted.count.files, 1);
+        assert_eq!(
+            res.deleted.files().unwrap()[0].path,
+            PathBuf::from("/link-to-file")
+        );
+    }
 }
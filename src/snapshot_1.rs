@@ -1,57 +1,216 @@
+use crate::checksum::CheckSum;
+use crate::path_key::PathKeys;
 use crate::snapshot::{FilesByChecksums, Snapshot};
-use crate::snapshot_1::Comparison::{Identical, Modified};
+use crate::snapshot_1::Comparison::{Identical, Modified, Respelled, TypeChanged};
 use crate::{file, stats};
-use file::File;
+use file::{Dir, File, Kind};
 use stats::Stats;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path;
 
 #[derive(Debug)]
 pub struct Snapshot1 {
     files_by_path: HashMap<path::PathBuf, File>,
+    files_by_dir: HashMap<path::PathBuf, u64>,
+    check_sums: HashSet<(Kind, CheckSum)>,
+    keys: PathKeys,
     total: Stats,
+    dirs_by_path: HashMap<path::PathBuf, Vec<Dir>>,
+    dir_total: u64,
+    empty_dirs: Vec<path::PathBuf>,
+    specials: u64,
 }
 
 impl Snapshot for Snapshot1 {
     fn add(&mut self, f1: File) {
         self.total.record(&f1);
-        if self.files_by_path.contains_key(&f1.path) {
-            panic!("Added duplicate file")
+        if f1.kind.is_special() {
+            self.specials += 1;
         }
-        self.files_by_path.insert(f1.path.clone(), f1);
+        self.check_sums.insert((f1.kind, f1.check_sum));
+        for dir in f1.path.ancestors().skip(1) {
+            if dir.file_name().is_some() {
+                *self.files_by_dir.entry(dir.to_path_buf()).or_default() += 1;
+            }
+        }
+        let key = self.keys.snap_1(&f1.path);
+        self.insert(key, f1);
+    }
+
+    fn add_dir(&mut self, d1: Dir) {
+        self.dir_total += 1;
+        if d1.is_empty {
+            self.empty_dirs.push(d1.path.clone());
+        }
+        let key = self.keys.snap_1(&d1.path);
+        self.dirs_by_path.entry(key).or_default().push(d1);
     }
 }
 
 pub enum Comparison {
     Identical,
+    // Identical, but the path is spelled differently.
+    Respelled,
     Modified,
+    // The entry has a different kind (e.g. a file became a symlink).
+    TypeChanged,
 }
 
 impl Snapshot1 {
+    #[allow(dead_code)]
     pub fn new() -> Snapshot1 {
+        return Snapshot1::new_with_keys(PathKeys::new(vec![], None, false));
+    }
+
+    pub fn new_with_keys(keys: PathKeys) -> Snapshot1 {
         return Snapshot1 {
             files_by_path: HashMap::new(),
+            files_by_dir: HashMap::new(),
+            check_sums: HashSet::new(),
+            keys,
             total: Stats::new(),
+            dirs_by_path: HashMap::new(),
+            dir_total: 0,
+            empty_dirs: vec![],
+            specials: 0,
         };
     }
 
+    // Stores a file in the internal lookup table. If two files end up with
+    // the same key, the one whose path resembles the key most closely takes
+    // precedence (see `precedence`). The other file falls back to a key that
+    // is closer to its original path.
+    fn insert(&mut self, key: path::PathBuf, f1: File) {
+        let mut pending = Some((key, f1));
+        while let Some((key, f)) = pending.take() {
+            let shall_keep_existing = self
+                .files_by_path
+                .get(&key)
+                .map(|existing| self.precedence(existing, &key) >= self.precedence(&f, &key))
+                .unwrap_or(false);
+            let displaced = if shall_keep_existing {
+                Some(f)
+            } else {
+                self.files_by_path.insert(key.clone(), f)
+            };
+            if let Some(d) = displaced {
+                pending = match self.precedence(&d, &key) {
+                    0 => Some((self.keys.snap_2(&d.path), d)),
+                    1 => Some((d.path.clone(), d)),
+                    _ => panic!("Added duplicate file"),
+                };
+            }
+        }
+    }
+
+    // Whether the file resides at `key` exactly (2), resides at an
+    // equivalent spelling of `key` (1), or was mapped onto `key` (0).
+    fn precedence(&self, f: &File, key: &path::Path) -> u8 {
+        if f.path == key {
+            return 2;
+        }
+        if self.keys.snap_2(&f.path) == key {
+            return 1;
+        }
+        return 0;
+    }
+
     // Processes a file from snapshot 2, and checks whether there is
     // a matching file in snapshot 1. If so, the file is removed from
     // the internal lookup table.
     pub fn digest(&mut self, f2: &File) -> Option<(Comparison, File)> {
-        return self.files_by_path.remove(&f2.path).map(|f1| {
-            if f1.equals(&f2) {
-                (Identical, f1)
-            } else {
+        let key = self.key_of(&f2.path);
+        return self.files_by_path.remove(&key).map(|f1| {
+            if f1.kind != f2.kind {
+                return (TypeChanged, f1);
+            }
+            if !f1.equals(f2) {
                 (Modified, f1)
+            } else if f1.path != f2.path && self.keys.snap_2(&f1.path) == key {
+                (Respelled, f1)
+            } else {
+                (Identical, f1)
             }
         });
     }
 
+    // Removes the file that a path from snapshot 2 would be compared with.
+    pub fn withdraw(&mut self, p2: &path::Path) -> Option<File> {
+        let key = self.key_of(p2);
+        return self.files_by_path.remove(&key);
+    }
+
+    // The file that a file from snapshot 2 would be compared with.
+    pub fn lookup(&self, p2: &path::Path) -> Option<&File> {
+        return self.files_by_path.get(&self.key_of(p2));
+    }
+
+    fn key_of(&self, p2: &path::Path) -> path::PathBuf {
+        // A file with the exact same spelling takes precedence over
+        // files with equivalent spellings.
+        let is_exact = self
+            .files_by_path
+            .get(p2)
+            .map(|f1| f1.path == p2)
+            .unwrap_or(false);
+        if is_exact {
+            return p2.to_path_buf();
+        }
+        return self.keys.snap_2(p2);
+    }
+
+    // Checks whether there is a matching directory in snapshot 1. If so,
+    // the directory is removed from the internal lookup table.
+    pub fn digest_dir(&mut self, d2: &Dir) -> Option<Dir> {
+        let key = if self.dirs_by_path.contains_key(&d2.path) {
+            d2.path.clone()
+        } else {
+            self.keys.snap_2(&d2.path)
+        };
+        let ds = self.dirs_by_path.get_mut(&key)?;
+        let i = ds.iter().position(|d1| d1.path == d2.path).unwrap_or(0);
+        let d1 = ds.remove(i);
+        if ds.is_empty() {
+            self.dirs_by_path.remove(&key);
+        }
+        return Some(d1);
+    }
+
+    // Returns the total number of directories, the empty directories, and
+    // the directories that had no match in snapshot 2.
+    pub fn conclude_dirs(&mut self) -> (u64, Vec<path::PathBuf>, Vec<path::PathBuf>) {
+        let mut remaining: Vec<path::PathBuf> = self
+            .dirs_by_path
+            .drain()
+            .flat_map(|(_, ds)| ds)
+            .map(|d| d.path)
+            .collect();
+        remaining.sort();
+        let mut empty = std::mem::take(&mut self.empty_dirs);
+        empty.sort();
+        return (self.dir_total, empty, remaining);
+    }
+
+    // The number of special files (FIFOs, sockets, device nodes).
+    pub fn specials(&self) -> u64 {
+        return self.specials;
+    }
+
     pub fn total(&self) -> &Stats {
         return &self.total;
     }
 
+    // Whether any file of the given kind in snapshot 1 has the given
+    // contents.
+    pub fn contains(&self, kind: Kind, check_sum: CheckSum) -> bool {
+        return self.check_sums.contains(&(kind, check_sum));
+    }
+
+    // The number of files in a directory (including sub-directories).
+    pub fn files_in_dir(&self, dir: &path::Path) -> u64 {
+        return self.files_by_dir.get(dir).copied().unwrap_or(0);
+    }
+
     // Processes the remainder of the internal lookup table, and puts
     // all remaining files into a lookup table “by checksums”.
     pub fn conclude(&mut self) -> (Stats, FilesByChecksums) {
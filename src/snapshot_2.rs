@@ -1,15 +1,120 @@
+use crate::baseline::Baseline;
+use crate::checksum;
+use crate::histogram::Histograms;
+use crate::policy::Policy;
+use crate::relocation::RelocateBy;
 use crate::snapshot::{FilesByChecksums, Snapshot};
 use crate::snapshot_1::Comparison;
+use crate::top::{TopBy, TopFiles};
+use crate::{dir_move, group, pairing, relocation, similarity};
 use crate::{file, report, snapshot_1};
-use file::File;
+use checksum::CheckSummer;
+use file::{Dir, File, Kind, SizeBytes, DIR_NAME};
 use report::Report;
 use snapshot_1::Snapshot1;
+use std::path;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug)]
 pub struct Snapshot2 {
     snap_1: Snapshot1,
     snap_2_remainder: FilesByChecksums,
+    block_candidates: Vec<(File, File)>,
     report: Report,
+    options: Options,
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    // The minimum share of files (in percent) that must have been moved
+    // from one directory to another, in order to report the directory
+    // as moved. `None` disables the detection of directory moves.
+    pub dir_move_threshold: Option<u64>,
+    // Regular files smaller than this (in bytes) are never matched up
+    // by their contents, i.e. they are neither reported as moved nor
+    // as copied. Small files, and empty files in particular, tend to
+    // have the same contents by coincidence.
+    pub min_move_size: u64,
+    // If set, deleted and added files whose contents is at least this
+    // similar (in percent) are reported as moved and modified.
+    pub similarity_threshold: Option<u64>,
+    // If set, deleted and added files with the same name are reported as
+    // relocated (regardless of their contents). If a size tolerance (in
+    // percent) is given, their sizes must be similar, too.
+    pub relocate_by: Option<RelocateBy>,
+    pub relocate_size_tolerance: Option<u64>,
+    // If set, modified files are compared block by block, in order to
+    // estimate how many bytes have actually changed.
+    pub block_size: Option<u64>,
+    // If set, the summary is broken down by directory, with directories
+    // cut off at this depth.
+    pub group_by_dir: Option<usize>,
+    // Whether the summary is broken down by file extension and by file
+    // type (as detected from the file contents).
+    pub group_by_ext: bool,
+    pub group_by_type: bool,
+    // Whether to compute size and age histograms per category.
+    pub histograms: bool,
+    // If set, the largest added, deleted and modified files are picked
+    // out, with modified files ranked as given.
+    pub top: Option<(usize, TopBy)>,
+    // If set, the expected changes are only counted (as such).
+    pub baseline: Option<Baseline>,
+    // If set, all changes are checked against the policy.
+    pub policy: Option<Policy>,
+    // Needed to tell whether shrunk files were truncated (otherwise they
+    // are reported as modified).
+    pub snap_1_root: Option<path::PathBuf>,
+}
+
+impl Options {
+    pub fn new() -> Options {
+        return Options {
+            dir_move_threshold: Some(100),
+            min_move_size: 1,
+            similarity_threshold: None,
+            relocate_by: None,
+            relocate_size_tolerance: None,
+            block_size: None,
+            group_by_dir: None,
+            group_by_ext: false,
+            group_by_type: false,
+            histograms: false,
+            top: None,
+            baseline: None,
+            policy: None,
+            snap_1_root: None,
+        };
+    }
+
+    fn is_matchable(&self, f: &File) -> bool {
+        return match f.kind {
+            Kind::Regular => f.size >= self.min_move_size,
+            Kind::Symlink => true,
+            _ => false,
+        };
+    }
+
+    // Whether the summary shall be broken down in any way.
+    fn is_grouped(&self) -> bool {
+        return self.group_by_dir.is_some()
+            || self.group_by_ext
+            || self.group_by_type
+            || self.histograms;
+    }
+
+    // Whether the snapshot-1 versions of the modified files are needed.
+    fn needs_modified_snap_1_files(&self) -> bool {
+        return self.top.is_some() || self.policy.is_some() || self.baseline.is_some();
+    }
+
+    // Splits off the files that may be paired up by the heuristics that
+    // are applied after content matching.
+    fn split_candidates(&self, fs: Vec<File>) -> (Vec<File>, Vec<File>) {
+        return fs
+            .into_iter()
+            .partition(|f| f.kind == Kind::Regular && self.is_matchable(f));
+    }
 }
 
 impl Snapshot for Snapshot2 {
@@ -17,29 +122,129 @@ impl Snapshot for Snapshot2 {
     // known in snapshot 1, store it in an internal remainder list.
     fn add(&mut self, f2: File) {
         self.report.total_snap_2.record(&f2);
+        if f2.kind.is_special() {
+            self.report.special_snap_2 += 1;
+        }
         self.snap_1
             .digest(&f2)
             .map(|(c, f1)| match c {
                 Comparison::Identical => {
                     self.report.identical.record(&f2);
+                    self.report.metadata_changed.record(&f1, &f2);
+                    let are_xattrs_changed = match (&f1.xattrs, &f2.xattrs) {
+                        (Some(x1), Some(x2)) => x1 != x2,
+                        _ => false,
+                    };
+                    if are_xattrs_changed {
+                        self.report.xattrs_changed.record(&f1, &f2);
+                    }
+                }
+                Comparison::Respelled => {
+                    self.report.moved.count.add(1, f2.size);
+                    self.report.respelled.record(&f1, &f2);
                 }
                 Comparison::Modified => {
-                    self.report.modified_snap_1.record(&f1);
-                    self.report.modified_snap_2.record(&f2);
+                    let is_regular = f1.kind == Kind::Regular && f2.kind == Kind::Regular;
+                    let prefix_check_sum = if f1.size == 0 {
+                        Some(CheckSummer::new().finalize())
+                    } else {
+                        f2.prefix_check_sum
+                    };
+                    if is_regular && f2.size > f1.size && prefix_check_sum == Some(f1.check_sum) {
+                        self.report.modified_snap_1.count.add(1, f1.size);
+                        self.report.modified_snap_2.count.add(1, f2.size);
+                        self.report.appended.record(&f1, &f2);
+                    } else if is_regular
+                        && f2.size < f1.size
+                        && f2.source_prefix_check_sum == Some(f2.check_sum)
+                    {
+                        self.report.modified_snap_1.count.add(1, f1.size);
+                        self.report.modified_snap_2.count.add(1, f2.size);
+                        self.report.truncated.record(&f1, &f2);
+                    } else {
+                        self.record_modified(f1, &f2);
+                    }
+                }
+                Comparison::TypeChanged => {
+                    self.report.type_changed.record(
+                        f1.kind.name(),
+                        f2.kind.name(),
+                        &f2.path,
+                        f2.size,
+                    );
                 }
             })
             .unwrap_or_else(|| {
-                self.snap_2_remainder.add(f2);
+                let d = Dir::new(f2.path.clone(), false);
+                if self.snap_1.digest_dir(&d).is_some() {
+                    self.report
+                        .type_changed
+                        .record(DIR_NAME, f2.kind.name(), &f2.path, f2.size);
+                } else {
+                    self.snap_2_remainder.add(f2);
+                }
             });
     }
+
+    fn add_dir(&mut self, d2: Dir) {
+        let dirs = &mut self.report.dirs;
+        dirs.total_snap_2 += 1;
+        if d2.is_empty {
+            dirs.empty_snap_2.push(d2.path.clone());
+        }
+        if let Some(f1) = self.snap_1.withdraw(&d2.path) {
+            self.report
+                .type_changed
+                .record(f1.kind.name(), DIR_NAME, &d2.path, 0);
+        } else if self.snap_1.digest_dir(&d2).is_none() {
+            dirs.added.push(d2.path);
+        }
+    }
+
+    // For files that have grown, the checksum of the leading bytes tells
+    // whether the new bytes were only appended.
+    fn prefix_length(&self, p: &path::Path) -> Option<SizeBytes> {
+        return self
+            .snap_1
+            .lookup(p)
+            .filter(|f1| f1.kind == Kind::Regular && f1.size > 0)
+            .map(|f1| f1.size);
+    }
+
+    // For files that have shrunk, the checksum of the leading bytes of the
+    // old version tells whether the file was only truncated.
+    fn truncation_source(&self, p: &path::Path, size: SizeBytes) -> Option<path::PathBuf> {
+        let root = self.options.snap_1_root.as_ref()?;
+        return self
+            .snap_1
+            .lookup(p)
+            .filter(|f1| f1.kind == Kind::Regular && f1.size > size)
+            .map(|f1| root.join(&f1.path));
+    }
 }
 
 impl Snapshot2 {
+    #[allow(dead_code)]
     pub fn new(source_snap: Snapshot1) -> Snapshot2 {
+        return Snapshot2::new_with_options(source_snap, Options::new());
+    }
+
+    pub fn new_with_options(source_snap: Snapshot1, options: Options) -> Snapshot2 {
+        let mut report = if options.is_grouped() {
+            // The breakdown needs the identical files, too.
+            Report::new_with_identical_files()
+        } else {
+            Report::new()
+        };
+        if options.needs_modified_snap_1_files() {
+            report = report.with_modified_snap_1_files();
+        }
         return Snapshot2 {
             snap_1: source_snap,
             snap_2_remainder: FilesByChecksums::new(),
-            report: Report::new(),
+            block_candidates: vec![],
+            report,
+            options,
         };
     }
 
@@ -47,24 +252,196 @@ impl Snapshot2 {
     pub fn conclude(&mut self) -> Report {
         let (total1, mut snap_1_remainder) = self.snap_1.conclude();
         self.report.total_snap_1 = total1;
+        self.report.special_snap_1 = self.snap_1.specials();
+
+        let (dir_total1, empty1, deleted_dirs) = self.snap_1.conclude_dirs();
+        let dirs = &mut self.report.dirs;
+        dirs.total_snap_1 = dir_total1;
+        dirs.empty_snap_1 = empty1;
+        dirs.deleted = deleted_dirs;
+        dirs.added.sort();
+        dirs.empty_snap_2.sort();
 
-        for (checksum, fs) in self.snap_2_remainder.drain() {
-            let f1 = snap_1_remainder.withdraw(&checksum);
-            if f1.is_none() {
-                for f1 in fs {
-                    self.report.added.record(&f1);
+        if let Some(block_size) = self.options.block_size {
+            let mut total = 0;
+            for (f1, f2) in std::mem::take(&mut self.block_candidates) {
+                match changed_bytes(&f1, &f2, block_size) {
+                    Some(changed) => {
+                        total += changed;
+                        self.report.changed_bytes.insert(f2.path, changed);
+                    }
+                    None => self.report.changed_bytes_skipped += 1,
                 }
+            }
+            for (f1, f2) in self.report.appended.pairs() {
+                total += f2.size - f1.size;
+            }
+            self.report.changed_bytes_total = Some(total);
+        }
+
+        let mut moves = vec![];
+        let mut added = vec![];
+        let mut deleted = vec![];
+        for ((kind, checksum), f2s) in self.snap_2_remainder.drain() {
+            let f1s = snap_1_remainder.withdraw_all(kind, checksum);
+            // All files in a group have the same kind and contents, hence
+            // the same size.
+            if !self.options.is_matchable(&f2s[0]) {
+                deleted.extend(f1s);
+                added.extend(f2s);
                 continue;
             }
-            self.report.moved.record(&f1.unwrap());
+            let (pairs, unpaired_f1s, unpaired_f2s) = pairing::pair_up(f1s, f2s);
+            moves.extend(pairs);
+            deleted.extend(unpaired_f1s);
+            for f2 in unpaired_f2s {
+                if self.snap_1.contains(kind, checksum) {
+                    self.report.copied.record(&f2);
+                } else {
+                    added.push(f2);
+                }
+            }
+        }
+        for (_, f1s) in snap_1_remainder.drain() {
+            deleted.extend(f1s);
         }
 
-        for (_, fs) in snap_1_remainder.drain() {
-            for f1 in fs {
-                self.report.deleted.record(&f1);
+        let (dir_moves, individual_moves) = match self.options.dir_move_threshold {
+            Some(threshold) => {
+                dir_move::aggregate(moves, |d| self.snap_1.files_in_dir(d), threshold)
+            }
+            None => (vec![], moves),
+        };
+        for d in &dir_moves {
+            self.report.moved.count.add(d.count.files, d.count.size);
+        }
+        self.report.dir_moves = dir_moves;
+        for (f1, f2) in individual_moves {
+            self.report.moved.record(&f1, &f2);
+        }
+
+        if let Some(threshold) = self.options.similarity_threshold {
+            let (candidates_1, others_1) = self.options.split_candidates(deleted);
+            let (candidates_2, others_2) = self.options.split_candidates(added);
+            let (pairs, unpaired_1, unpaired_2) =
+                similarity::pair_similar(candidates_1, candidates_2, threshold);
+            for p in &pairs {
+                self.report.moved_modified_snap_1.add(1, p.f1.size);
+                self.report.moved_modified_snap_2.add(1, p.f2.size);
+            }
+            self.report.moved_modified = pairs;
+            deleted = others_1.into_iter().chain(unpaired_1).collect();
+            added = others_2.into_iter().chain(unpaired_2).collect();
+        }
+        if let Some(by) = self.options.relocate_by {
+            let (candidates_1, others_1) = self.options.split_candidates(deleted);
+            let (candidates_2, others_2) = self.options.split_candidates(added);
+            let (pairs, unpaired_1, unpaired_2) = relocation::pair_relocated(
+                candidates_1,
+                candidates_2,
+                by,
+                self.options.relocate_size_tolerance,
+            );
+            for (f1, f2) in &pairs {
+                self.report.relocated_snap_1.add(1, f1.size);
+                self.report.relocated.record(f1, f2);
             }
+            deleted = others_1.into_iter().chain(unpaired_1).collect();
+            added = others_2.into_iter().chain(unpaired_2).collect();
+        }
+        for f1 in deleted {
+            self.record_deleted(&f1);
+        }
+        for f2 in added {
+            self.record_added(&f2);
+        }
+        if let Some(baseline) = &self.options.baseline {
+            self.report.expected = Some(baseline.apply(&mut self.report));
+        }
+        if let Some(depth) = self.options.group_by_dir {
+            self.report.dir_groups = Some(group::group_by(&self.report, |f| {
+                return group::dir_key(&f.path, depth);
+            }));
+        }
+        if self.options.group_by_ext {
+            self.report.ext_groups = Some(group::group_by(&self.report, |f| {
+                return group::ext_key(&f.path);
+            }));
+        }
+        if self.options.group_by_type {
+            self.report.type_groups = Some(group::group_by(&self.report, group::type_key));
+        }
+        if self.options.histograms {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            self.report.histograms = Some(Histograms::from(&self.report, now));
+        }
+        if let Some((n, by)) = self.options.top {
+            self.report.top = Some(TopFiles::from(&self.report, n, by));
+        }
+        if let Some(policy) = &self.options.policy {
+            self.report.policy_violations = Some(policy.check(&self.report));
         }
 
         return std::mem::replace(&mut self.report, Report::new());
     }
+
+    fn record_modified(&mut self, f1: File, f2: &File) {
+        self.report.modified_snap_1.record(&f1);
+        self.report.modified_snap_2.record(f2);
+        if is_suspicious(&f1, f2) {
+            self.report.suspicious.record(f2);
+        }
+        if self.options.block_size.is_some() && f1.kind == Kind::Regular && f2.kind == Kind::Regular
+        {
+            self.block_candidates.push((f1, f2.clone()));
+        }
+    }
+
+    fn record_added(&mut self, f2: &File) {
+        self.report.added.record(f2);
+        if f2.kind == Kind::Regular && f2.size == 0 {
+            self.report.added_empty.add(1, 0);
+        }
+    }
+
+    fn record_deleted(&mut self, f1: &File) {
+        self.report.deleted.record(f1);
+        if f1.kind == Kind::Regular && f1.size == 0 {
+            self.report.deleted_empty.add(1, 0);
+        }
+    }
+}
+
+// Counts the bytes of the second file that lie in blocks which differ from
+// the blocks at the same offsets in the first file. Without block checksums
+// (e.g., if a file couldn’t be read), the files cannot be compared.
+fn changed_bytes(f1: &File, f2: &File, block_size: SizeBytes) -> Option<SizeBytes> {
+    let (b1, b2) = match (&f1.block_check_sums, &f2.block_check_sums) {
+        (Some(b1), Some(b2)) => (b1, b2),
+        _ => return None,
+    };
+    let mut changed = 0;
+    for (i, c2) in b2.iter().enumerate() {
+        if b1.get(i) != Some(c2) {
+            let offset = i as SizeBytes * block_size;
+            changed += block_size.min(f2.size - offset);
+        }
+    }
+    return Some(changed);
+}
+
+// Whether the contents of a file has changed, even though its size and
+// modification time are the same. On archival storage, this almost
+// always indicates silent corruption (“bit rot”).
+fn is_suspicious(f1: &File, f2: &File) -> bool {
+    if f1.kind != Kind::Regular || f2.kind != Kind::Regular || f1.size != f2.size {
+        return false;
+    }
+    return match (&f1.meta, &f2.meta) {
+        (Some(m1), Some(m2)) => m1.mtime == m2.mtime && m1.mtime_nsec == m2.mtime_nsec,
+        _ => false,
+    };
 }
@@ -1,5 +1,6 @@
 use crate::file;
 use file::{File, SizeBytes};
+use std::path;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Count {
@@ -16,6 +17,24 @@ impl Count {
         self.size += size;
         self.files += files;
     }
+
+    pub fn remove(&mut self, files: u64, size: SizeBytes) {
+        self.size -= size;
+        self.files -= files;
+    }
+}
+
+// Removes the items for which `is_removed` is true (keeping the order of
+// the others), and returns them.
+pub fn withdraw_if<T, F>(items: &mut Vec<T>, mut is_removed: F) -> Vec<T>
+where
+    F: FnMut(&T) -> bool,
+{
+    let (removed, kept) = std::mem::take(items)
+        .into_iter()
+        .partition(|item| is_removed(item));
+    *items = kept;
+    return removed;
 }
 
 #[derive(Debug)]
@@ -55,13 +74,132 @@ impl Stats {
         }
         return Some(&self.files);
     }
+
+    // Removes the matching files (only if files are stored).
+    pub fn withdraw_if<F: FnMut(&File) -> bool>(&mut self, is_removed: F) -> Vec<File> {
+        let removed = withdraw_if(&mut self.files, is_removed);
+        for f in &removed {
+            self.count.remove(1, f.size);
+        }
+        return removed;
+    }
+}
+
+// Like `Stats`, but for files that were matched up between both
+// snapshots. The count is based on the file from snapshot 2.
+#[derive(Debug)]
+pub struct PairStats {
+    pub count: Count,
+    pairs: Vec<(File, File)>,
+}
+
+impl PairStats {
+    pub fn new() -> PairStats {
+        return PairStats {
+            count: Count::new(),
+            pairs: vec![],
+        };
+    }
+
+    pub fn record(&mut self, f1: &File, f2: &File) {
+        self.count.add(1, f2.size);
+        self.pairs.push((f1.clone(), f2.clone()));
+    }
+
+    pub fn pairs(&self) -> &Vec<(File, File)> {
+        return &self.pairs;
+    }
+
+    pub fn withdraw_if<F>(&mut self, is_removed: F) -> Vec<(File, File)>
+    where
+        F: FnMut(&(File, File)) -> bool,
+    {
+        let removed = withdraw_if(&mut self.pairs, is_removed);
+        for (_, f2) in &removed {
+            self.count.remove(1, f2.size);
+        }
+        return removed;
+    }
+}
+
+// Statistics about directories. Directories are matched up by their
+// paths only.
+#[derive(Debug)]
+pub struct DirStats {
+    pub total_snap_1: u64,
+    pub total_snap_2: u64,
+    pub added: Vec<path::PathBuf>,
+    pub deleted: Vec<path::PathBuf>,
+    pub empty_snap_1: Vec<path::PathBuf>,
+    pub empty_snap_2: Vec<path::PathBuf>,
+}
+
+impl DirStats {
+    pub fn new() -> DirStats {
+        return DirStats {
+            total_snap_1: 0,
+            total_snap_2: 0,
+            added: vec![],
+            deleted: vec![],
+            empty_snap_1: vec![],
+            empty_snap_2: vec![],
+        };
+    }
+}
+
+// Entries whose kind differs between both snapshots (e.g. a file that
+// became a directory). The count is based on snapshot 2, where
+// directories have no size.
+#[derive(Debug)]
+pub struct TypeChanges {
+    pub count: Count,
+    changes: Vec<(&'static str, &'static str, path::PathBuf, SizeBytes)>,
+}
+
+impl TypeChanges {
+    pub fn new() -> TypeChanges {
+        return TypeChanges {
+            count: Count::new(),
+            changes: vec![],
+        };
+    }
+
+    pub fn record(
+        &mut self,
+        from: &'static str,
+        to: &'static str,
+        p: &path::Path,
+        size: SizeBytes,
+    ) {
+        self.count.add(1, size);
+        self.changes.push((from, to, p.to_path_buf(), size));
+    }
+
+    pub fn changes(&self) -> &Vec<(&'static str, &'static str, path::PathBuf, SizeBytes)> {
+        return &self.changes;
+    }
+
+    // Removes the matching changes, and returns their paths and sizes.
+    pub fn withdraw_if<F>(&mut self, mut is_removed: F) -> Vec<(path::PathBuf, SizeBytes)>
+    where
+        F: FnMut(&path::Path) -> bool,
+    {
+        let removed = withdraw_if(&mut self.changes, |(_, _, p, _)| is_removed(p));
+        for (_, _, _, size) in &removed {
+            self.count.remove(1, *size);
+        }
+        return removed
+            .into_iter()
+            .map(|(_, _, p, size)| (p, size))
+            .collect();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::file::File;
     use crate::stats;
-    use stats::Stats;
+    use stats::{PairStats, Stats};
 
     #[test]
     fn new_stats_is_empty() {
@@ -77,4 +215,28 @@ mod tests {
         assert_eq!(r.count.size, 3);
         assert_eq!(r.count.files, 1);
     }
+
+    #[test]
+    fn pair_stats_records_files() {
+        let mut r = PairStats::new();
+        r.record(
+            &File::from_strings("/tmp/x", "Foo"),
+            &File::from_strings("/tmp/X", "Foo"),
+        );
+        assert_eq!(r.count.size, 3);
+        assert_eq!(r.count.files, 1);
+        assert_eq!(r.pairs()[0].1.path.to_str(), Some("/tmp/X"));
+    }
+
+    #[test]
+    fn stats_withdraws_files() {
+        let mut r = Stats::new_with_file_storage();
+        r.record(&File::from_strings("/tmp/x", "Foo"));
+        r.record(&File::from_strings("/tmp/y", "Bar!"));
+        let removed = r.withdraw_if(|f| f.path.ends_with("y"));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(r.count.files, 1);
+        assert_eq!(r.count.size, 3);
+        assert_eq!(r.files().unwrap()[0].path.to_str(), Some("/tmp/x"));
+    }
 }
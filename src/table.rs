@@ -0,0 +1,457 @@
+use crate::file::SizeBytes;
+use crate::format::{dec, size_human};
+use crate::printer::{Colours, Printer};
+use crate::stats::Count;
+
+// A row in the summary table. A row belongs to a section (e.g. “TOTAL”),
+// which is only printed for the first row of that section.
+pub struct Row {
+    section: &'static str,
+    colour: &'static str,
+    label: String,
+    count: Option<Count>,
+    shows_size: bool,
+    note: Option<String>,
+}
+
+const LABEL_WIDTH: usize = 12;
+const SUB_INDENT: &str = "  ";
+
+impl Row {
+    pub fn new(section: &'static str, colour: &'static str, label: &str, count: Count) -> Row {
+        return Row {
+            section,
+            colour,
+            label: label.to_string(),
+            count: Some(count),
+            shows_size: true,
+            note: None,
+        };
+    }
+
+    // A row that further breaks down the row above it.
+    pub fn sub(colour: &'static str, label: &str, count: Count) -> Row {
+        return Row::new("", colour, &format!("{SUB_INDENT}{label}"), count);
+    }
+
+    pub fn blank() -> Row {
+        return Row {
+            section: "",
+            colour: "",
+            label: "".to_string(),
+            count: None,
+            shows_size: true,
+            note: None,
+        };
+    }
+
+    // Leaves the bytes column blank, e.g. for rows that count folders.
+    pub fn without_size(mut self) -> Row {
+        self.shows_size = false;
+        return self;
+    }
+
+    pub fn with_note(mut self, note: String) -> Row {
+        self.note = Some(note);
+        return self;
+    }
+}
+
+pub fn print_summary_table(printer: &mut dyn Printer, rows: Vec<Row>) {
+    let files_header = "FILES".to_string();
+    let size_header = "BYTES".to_string();
+    let files: Vec<String> = rows
+        .iter()
+        .map(|r| r.count.map(|c| dec(c.files as i128)).unwrap_or_default())
+        .collect();
+    let size: Vec<String> = rows
+        .iter()
+        .map(|r| {
+            r.count
+                .filter(|_| r.shows_size)
+                .map(|c| dec(c.size as i128))
+                .unwrap_or_default()
+        })
+        .collect();
+    let f = files
+        .iter()
+        .chain([&files_header])
+        .map(|s| s.len())
+        .max()
+        .unwrap();
+    let b = size
+        .iter()
+        .chain([&size_header])
+        .map(|s| s.len())
+        .max()
+        .unwrap();
+    let l = rows
+        .iter()
+        .map(|r| r.label.chars().count() + 1)
+        .chain([LABEL_WIDTH])
+        .max()
+        .unwrap();
+    let byte_markers = {
+        let markers = "T   G   M   K   B";
+        markers[markers.len() - b.min(markers.len())..].to_string()
+    };
+    let Colours {
+        dark: drk,
+        brown: brn,
+        reset: rst,
+        bold: bld,
+        ..
+    } = *printer.colours();
+    let mut out = "\n".to_string();
+    out.push_str(&format!(
+        "{bld}{: <LABEL_WIDTH$}{: <l$}{: >f$}     {: >b$}{rst}\n",
+        "", "", files_header, size_header
+    ));
+    out.push_str(&format!(
+        "{bld}{: <LABEL_WIDTH$}{rst}{drk}{: <l$}{: >f$}     {: >b$}{rst}\n",
+        "", "", "", byte_markers
+    ));
+    for (i, r) in rows.iter().enumerate() {
+        if r.count.is_none() {
+            out.push_str(&format!("{bld}{: <LABEL_WIDTH$}{rst}\n", r.section));
+            continue;
+        }
+        let note = r
+            .note
+            .as_ref()
+            .map(|n| format!("{brn} ({n})"))
+            .unwrap_or_default();
+        out.push_str(&format!(
+            "{bld}{: <LABEL_WIDTH$}{rst}{}{: <l$}{: >f$}     {: >b$}{}{rst}\n",
+            r.section, r.colour, r.label, files[i], size[i], note,
+        ));
+    }
+    printer.print(out);
+}
+
+// Prints a breakdown of the summary, with one line per key (e.g. per
+// folder) and one column per category. Each cell shows the number of
+// files and their size; cells without files are left blank.
+pub fn print_breakdown_table(
+    printer: &mut dyn Printer,
+    title: &str,
+    columns: &[(&str, &'static str)],
+    rows: &[(String, Vec<Count>)],
+) {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(_, counts)| {
+            return counts
+                .iter()
+                .map(|c| match c.files {
+                    0 => "".to_string(),
+                    _ => format!("{} {}", dec(c.files as i128), size_human(c.size)),
+                })
+                .collect();
+        })
+        .collect();
+    let k = rows
+        .iter()
+        .map(|(key, _)| key.chars().count())
+        .chain([title.chars().count(), LABEL_WIDTH - 2])
+        .max()
+        .unwrap();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, (header, _))| {
+            return cells
+                .iter()
+                .map(|r| r[i].chars().count())
+                .chain([header.chars().count()])
+                .max()
+                .unwrap();
+        })
+        .collect();
+    let Colours {
+        light: lgt,
+        reset: rst,
+        bold: bld,
+        ..
+    } = *printer.colours();
+    let mut out = format!("\n{bld}{: <k$}{rst}", title);
+    for (i, (header, colour)) in columns.iter().enumerate() {
+        out.push_str(&format!(
+            "  {bld}{colour}{: >w$}{rst}",
+            header,
+            w = widths[i]
+        ));
+    }
+    out.push('\n');
+    for (r, (key, _)) in rows.iter().enumerate() {
+        out.push_str(&format!("{lgt}{: <k$}{rst}", key));
+        for (i, (_, colour)) in columns.iter().enumerate() {
+            out.push_str(&format!(
+                "  {colour}{: >w$}{rst}",
+                cells[r][i],
+                w = widths[i]
+            ));
+        }
+        out.push('\n');
+    }
+    printer.print(out);
+}
+
+// Prints a histogram per row, with one column per bucket. Each cell
+// shows the number of files, along with a bar that is scaled relative
+// to the largest bucket of the row.
+pub fn print_histogram_table(
+    printer: &mut dyn Printer,
+    title: &str,
+    buckets: &[&str],
+    rows: &[(&str, &'static str, Vec<u64>)],
+) {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|(_, _, counts)| {
+            let max = counts.iter().copied().max().unwrap_or(0);
+            return counts
+                .iter()
+                .map(|n| match n {
+                    0 => "".to_string(),
+                    n => {
+                        let bar = BARS[((n * 8).div_ceil(max) - 1) as usize];
+                        format!("{} {}", bar, dec(*n as i128))
+                    }
+                })
+                .collect();
+        })
+        .collect();
+    let k = rows
+        .iter()
+        .map(|(label, _, _)| label.chars().count())
+        .chain([title.chars().count(), LABEL_WIDTH - 2])
+        .max()
+        .unwrap();
+    let widths: Vec<usize> = buckets
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            return cells
+                .iter()
+                .map(|r| r[i].chars().count())
+                .chain([b.chars().count()])
+                .max()
+                .unwrap();
+        })
+        .collect();
+    let Colours {
+        reset: rst,
+        bold: bld,
+        ..
+    } = *printer.colours();
+    let mut out = format!("\n{bld}{: <k$}{rst}", title);
+    for (i, b) in buckets.iter().enumerate() {
+        out.push_str(&format!("  {bld}{: >w$}{rst}", b, w = widths[i]));
+    }
+    out.push('\n');
+    for (r, (label, colour, _)) in rows.iter().enumerate() {
+        out.push_str(&format!("{colour}{: <k$}", label));
+        for (i, cell) in cells[r].iter().enumerate() {
+            out.push_str(&format!("  {: >w$}", cell, w = widths[i]));
+        }
+        out.push_str(&format!("{rst}\n"));
+    }
+    printer.print(out);
+}
+
+// A file in a list: its size, its path and an optional note.
+pub type FileLine = (SizeBytes, String, Option<String>);
+
+// Prints lists of files (e.g. the largest added files), one file per
+// line, with its size, its path and an optional note. Empty lists are
+// left out.
+pub fn print_file_lists(
+    printer: &mut dyn Printer,
+    section: &str,
+    lists: &[(&str, &'static str, Vec<FileLine>)],
+) {
+    let lists: Vec<_> = lists.iter().filter(|(_, _, fs)| !fs.is_empty()).collect();
+    let l = lists
+        .iter()
+        .map(|(label, _, _)| label.chars().count() + 1)
+        .chain([LABEL_WIDTH])
+        .max()
+        .unwrap();
+    let s = lists
+        .iter()
+        .flat_map(|(_, _, fs)| fs.iter().map(|(size, _, _)| size_human(*size).len()))
+        .max()
+        .unwrap_or(0);
+    let Colours {
+        brown: brn,
+        reset: rst,
+        bold: bld,
+        ..
+    } = *printer.colours();
+    let mut out = "\n".to_string();
+    let mut section = section;
+    for (label, colour, files) in lists {
+        let mut label: &str = label;
+        for (size, path, note) in files {
+            let note = note
+                .as_ref()
+                .map(|n| format!("{brn} ({n})"))
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "{bld}{: <LABEL_WIDTH$}{rst}{colour}{: <l$}{: >s$}  {}{}{rst}\n",
+                section,
+                label,
+                size_human(*size),
+                path,
+                note
+            ));
+            section = "";
+            label = "";
+        }
+    }
+    printer.print(out);
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::printer::MockPrinter;
+    use crate::stats::Count;
+    use crate::table::{
+        print_breakdown_table, print_file_lists, print_histogram_table, print_summary_table, Row,
+    };
+
+    fn count(files: u64, size: u64) -> Count {
+        let mut c = Count::new();
+        c.add(files, size);
+        return c;
+    }
+
+    #[test]
+    fn prints_table() {
+        let mut p = MockPrinter::new();
+        print_summary_table(
+            &mut p,
+            vec![
+                Row::new("TOTAL", "", "Snap 1", count(87243, 98407188994)),
+                Row::blank(),
+                Row::new("OF WHICH", "", "Moved", count(38, 134217728)),
+                Row::sub("", "Respelled", count(2, 1024)),
+                Row::new("", "", "Modified", count(147, 671088644))
+                    .with_note("+282,172".to_string()),
+                Row::new("FOLDERS", "", "Added", count(3, 0)).without_size(),
+            ],
+        );
+        assert_eq!(
+            p.flush(),
+            [
+                "",
+                "                         FILES              BYTES",
+                "                                    G   M   K   B",
+                "TOTAL       Snap 1      87,243     98,407,188,994",
+                "            ",
+                "OF WHICH    Moved           38        134,217,728",
+                "              Respelled      2              1,024",
+                "            Modified       147        671,088,644 (+282,172)",
+                "FOLDERS     Added            3                   ",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+
+    #[test]
+    fn widens_label_column() {
+        let mut p = MockPrinter::new();
+        print_summary_table(
+            &mut p,
+            vec![Row::new("OF WHICH", "", "Moved+Modified", count(1, 2))],
+        );
+        assert_eq!(
+            p.flush(),
+            "
+                           FILES     BYTES
+                                     K   B
+OF WHICH    Moved+Modified     1         2
+"
+        );
+    }
+
+    #[test]
+    fn prints_breakdown_table() {
+        let mut p = MockPrinter::new();
+        print_breakdown_table(
+            &mut p,
+            "FOLDER",
+            &[("ADDED", ""), ("DELETED", "")],
+            &[
+                ("photos".to_string(), vec![count(12, 34_567), Count::new()]),
+                (".".to_string(), vec![count(1, 5), count(1_234, 1_234_567)]),
+            ],
+        );
+        assert_eq!(
+            p.flush(),
+            "
+FOLDER          ADDED      DELETED
+photos      12 34.5 K             
+.               1 5 B  1,234 1.2 M
+"
+        );
+    }
+
+    #[test]
+    fn prints_histogram_table() {
+        let mut p = MockPrinter::new();
+        print_histogram_table(
+            &mut p,
+            "SIZE",
+            &["<4K", "<64K", ">=64K"],
+            &[
+                ("Added", "", vec![16, 0, 1_234]),
+                ("Deleted", "", vec![3, 3, 0]),
+            ],
+        );
+        assert_eq!(
+            p.flush(),
+            "
+SIZE         <4K  <64K    >=64K
+Added       ▁ 16        █ 1,234
+Deleted      █ 3   █ 3         
+"
+        );
+    }
+
+    #[test]
+    fn prints_file_lists() {
+        let mut p = MockPrinter::new();
+        print_file_lists(
+            &mut p,
+            "LARGEST",
+            &[
+                (
+                    "Added",
+                    "",
+                    vec![
+                        (1_234_567, "a.mp4".to_string(), None),
+                        (12, "b.txt".to_string(), None),
+                    ],
+                ),
+                ("Deleted", "", vec![]),
+                (
+                    "Modified",
+                    "",
+                    vec![(2_000, "c.db".to_string(), Some("+1,000".to_string()))],
+                ),
+            ],
+        );
+        assert_eq!(
+            p.flush(),
+            "
+LARGEST     Added       1.2 M  a.mp4
+                         12 B  b.txt
+            Modified    2.0 K  c.db (+1,000)
+"
+        );
+    }
+}
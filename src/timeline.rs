@@ -0,0 +1,126 @@
+use crate::file::{Dir, File, SizeBytes};
+use crate::printer::Printer;
+use crate::report::Report;
+use crate::snapshot::Snapshot;
+use crate::snapshot_1::Snapshot1;
+use crate::snapshot_2::Snapshot2;
+use crate::table::print_breakdown_table;
+use std::path;
+
+// A snapshot that is compared with the previous one, and at the same time
+// recorded for the comparison with the next one. That way, each snapshot
+// of a timeline is only read (and hashed) once.
+#[derive(Debug)]
+pub struct Tee {
+    snap_2: Snapshot2,
+    next: Snapshot1,
+}
+
+impl Snapshot for Tee {
+    fn add(&mut self, f: File) {
+        self.next.add(f.clone());
+        self.snap_2.add(f);
+    }
+
+    fn add_dir(&mut self, d: Dir) {
+        self.next.add_dir(d.clone());
+        self.snap_2.add_dir(d);
+    }
+
+    fn prefix_length(&self, p: &path::Path) -> Option<SizeBytes> {
+        return self.snap_2.prefix_length(p);
+    }
+
+    fn truncation_source(&self, p: &path::Path, size: SizeBytes) -> Option<path::PathBuf> {
+        return self.snap_2.truncation_source(p, size);
+    }
+}
+
+impl Tee {
+    pub fn new(snap_2: Snapshot2, next: Snapshot1) -> Tee {
+        return Tee { snap_2, next };
+    }
+
+    // Returns the report of the comparison with the previous snapshot, and
+    // the snapshot itself (to be compared with the next one).
+    pub fn conclude(mut self) -> (Report, Snapshot1) {
+        return (self.snap_2.conclude(), self.next);
+    }
+}
+
+// The name of a snapshot, as shown in the timeline.
+pub fn snap_name(root: &path::Path) -> String {
+    return root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.display().to_string());
+}
+
+// Prints one line per interval (i.e. per pair of consecutive snapshots),
+// with the added, deleted and modified files.
+pub fn print_timeline(printer: &mut dyn Printer, intervals: &[(String, Report)]) {
+    let colours = *printer.colours();
+    let rows: Vec<_> = intervals
+        .iter()
+        .map(|(name, r)| {
+            return (
+                name.clone(),
+                vec![r.added.count, r.deleted.count, r.modified_snap_2.count],
+            );
+        })
+        .collect();
+    print_breakdown_table(
+        printer,
+        "TIMELINE",
+        &[
+            ("Added", colours.green),
+            ("Deleted", colours.red),
+            ("Modified", colours.yellow),
+        ],
+        &rows,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::printer::MockPrinter;
+    use crate::snapshot::Snapshot;
+    use crate::snapshot_1::Snapshot1;
+    use crate::snapshot_2::Snapshot2;
+    use crate::timeline::{print_timeline, Tee};
+
+    #[test]
+    fn compares_consecutive_snapshots() {
+        let mut s1 = Snapshot1::new();
+        s1.add(File::from_strings("a", "a"));
+        s1.add(File::from_strings("b", "b"));
+
+        let mut tee = Tee::new(Snapshot2::new(s1), Snapshot1::new());
+        tee.add(File::from_strings("a", "aa"));
+        let (r1, s2) = tee.conclude();
+        assert_eq!(r1.modified_snap_2.count.files, 1);
+        assert_eq!(r1.deleted.count.files, 1);
+
+        let mut s3 = Snapshot2::new(s2);
+        s3.add(File::from_strings("a", "aa"));
+        s3.add(File::from_strings("c", "ccc"));
+        let r2 = s3.conclude();
+        assert_eq!(r2.identical.count.files, 1);
+        assert_eq!(r2.added.count.files, 1);
+        assert_eq!(r2.added.count.size, 3);
+
+        let mut printer = MockPrinter::new();
+        print_timeline(
+            &mut printer,
+            &[("1 → 2".to_string(), r1), ("2 → 3".to_string(), r2)],
+        );
+        assert_eq!(
+            printer.flush(),
+            "\n\
+            TIMELINE    Added  Deleted  Modified\n\
+            1 → 2                1 1 B     1 2 B\n\
+            2 → 3       1 3 B                   \n"
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use crate::file::File;
+use crate::report::Report;
+use clap::ValueEnum;
+
+// How modified files are ranked.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum TopBy {
+    /// The size of the file (as in snapshot 2).
+    Bytes,
+    /// How much the file has grown or shrunk.
+    Delta,
+}
+
+// The largest added, deleted and modified files.
+#[derive(Debug)]
+pub struct TopFiles {
+    pub added: Vec<File>,
+    pub deleted: Vec<File>,
+    pub modified: Vec<(File, File)>,
+}
+
+impl TopFiles {
+    // Picks the `n` largest files per category. Ties are broken by path.
+    pub fn from(report: &Report, n: usize, by: TopBy) -> TopFiles {
+        let largest = |files: Option<&Vec<File>>| {
+            let mut fs: Vec<File> = files.into_iter().flatten().cloned().collect();
+            fs.sort_by(|f1, f2| f2.size.cmp(&f1.size).then_with(|| f1.path.cmp(&f2.path)));
+            fs.truncate(n);
+            return fs;
+        };
+
+        let mut modified: Vec<(File, File)> = report
+            .modified_snap_1
+            .files()
+            .into_iter()
+            .flatten()
+            .cloned()
+            .zip(
+                report
+                    .modified_snap_2
+                    .files()
+                    .into_iter()
+                    .flatten()
+                    .cloned(),
+            )
+            .chain(report.appended.pairs().iter().cloned())
+            .chain(report.truncated.pairs().iter().cloned())
+            .collect();
+        let rank = |(f1, f2): &(File, File)| {
+            return match by {
+                TopBy::Bytes => f2.size,
+                TopBy::Delta => f2.size.abs_diff(f1.size),
+            };
+        };
+        modified.sort_by(|p1, p2| {
+            return rank(p2)
+                .cmp(&rank(p1))
+                .then_with(|| p1.1.path.cmp(&p2.1.path));
+        });
+        modified.truncate(n);
+
+        return TopFiles {
+            added: largest(report.added.files()),
+            deleted: largest(report.deleted.files()),
+            modified,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file::File;
+    use crate::report::Report;
+    use crate::top::{TopBy, TopFiles};
+
+    #[test]
+    fn picks_largest_files() {
+        let mut r = Report::new().with_modified_snap_1_files();
+        for (p, c) in [("a", "1"), ("b", "333"), ("c", "22"), ("d", "333")] {
+            r.added.record(&File::from_strings(p, c));
+        }
+        r.modified_snap_1.record(&File::from_strings("m", "1"));
+        r.modified_snap_2.record(&File::from_strings("m", "1234"));
+        r.modified_snap_1.record(&File::from_strings("n", "12345"));
+        r.modified_snap_2.record(&File::from_strings("n", "54321"));
+
+        let top = TopFiles::from(&r, 2, TopBy::Bytes);
+        let paths: Vec<&str> = top.added.iter().map(|f| f.path.to_str().unwrap()).collect();
+        assert_eq!(paths, vec!["b", "d"]);
+        assert!(top.deleted.is_empty());
+        assert_eq!(top.modified[0].1.path.to_str(), Some("n"));
+
+        let top = TopFiles::from(&r, 1, TopBy::Delta);
+        assert_eq!(top.modified.len(), 1);
+        assert_eq!(top.modified[0].1.path.to_str(), Some("m"));
+    }
+}
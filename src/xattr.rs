@@ -0,0 +1,160 @@
+use crate::checksum::{CheckSum, CheckSummer};
+use std::ffi::{CString, OsString};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::{io, path};
+
+// The extended attributes of a file (including POSIX ACLs, which are
+// stored as `system.posix_acl_*` attributes). Only the checksums of the
+// values are kept, sorted by attribute name.
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+pub struct Xattrs(Vec<(OsString, CheckSum)>);
+
+impl Xattrs {
+    #[allow(dead_code)]
+    pub fn new(mut attrs: Vec<(OsString, CheckSum)>) -> Xattrs {
+        attrs.sort();
+        return Xattrs(attrs);
+    }
+
+    // Reads the extended attributes of a file, following symlinks only if
+    // requested. File systems without support for extended attributes
+    // yield none.
+    pub fn read(p: &path::Path, follow: bool) -> io::Result<Xattrs> {
+        let c_path = CString::new(p.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let names = match list(&c_path, follow) {
+            Ok(names) => names,
+            Err(e) if e.raw_os_error() == Some(libc::ENOTSUP) => return Ok(Xattrs(vec![])),
+            Err(e) => return Err(e),
+        };
+        let mut attrs = vec![];
+        for name in names.split(|b| *b == 0).filter(|n| !n.is_empty()) {
+            let c_name = CString::new(name).unwrap();
+            let value = get(&c_path, &c_name, follow)?;
+            let check_sum = CheckSummer::new().consume(&value).finalize();
+            attrs.push((OsString::from_vec(name.to_vec()), check_sum));
+        }
+        attrs.sort();
+        return Ok(Xattrs(attrs));
+    }
+
+    // The names of all attributes that were added, removed or changed.
+    pub fn diff(&self, other: &Xattrs) -> Vec<OsString> {
+        let mut names: Vec<OsString> = self
+            .0
+            .iter()
+            .filter(|a| !other.0.contains(a))
+            .chain(other.0.iter().filter(|a| !self.0.contains(a)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+        return names;
+    }
+}
+
+// Calls a function that fills a buffer, whose required size is queried
+// first (by passing an empty buffer). The size might change in between.
+fn read_sized<F>(f: F) -> io::Result<Vec<u8>>
+where
+    F: Fn(*mut libc::c_void, usize) -> libc::ssize_t,
+{
+    loop {
+        let size = f(std::ptr::null_mut(), 0);
+        if size < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = vec![0u8; size as usize];
+        let n = f(buffer.as_mut_ptr() as *mut libc::c_void, buffer.len());
+        if n < 0 {
+            let e = io::Error::last_os_error();
+            if e.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(e);
+        }
+        buffer.truncate(n as usize);
+        return Ok(buffer);
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn list(p: &CString, follow: bool) -> io::Result<Vec<u8>> {
+    return read_sized(|buf, size| unsafe {
+        let buf = buf as *mut libc::c_char;
+        if follow {
+            libc::listxattr(p.as_ptr(), buf, size)
+        } else {
+            libc::llistxattr(p.as_ptr(), buf, size)
+        }
+    });
+}
+
+#[cfg(target_os = "linux")]
+fn get(p: &CString, name: &CString, follow: bool) -> io::Result<Vec<u8>> {
+    return read_sized(|buf, size| unsafe {
+        if follow {
+            libc::getxattr(p.as_ptr(), name.as_ptr(), buf, size)
+        } else {
+            libc::lgetxattr(p.as_ptr(), name.as_ptr(), buf, size)
+        }
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn options(follow: bool) -> libc::c_int {
+    return if follow { 0 } else { libc::XATTR_NOFOLLOW };
+}
+
+#[cfg(target_os = "macos")]
+fn list(p: &CString, follow: bool) -> io::Result<Vec<u8>> {
+    return read_sized(|buf, size| unsafe {
+        libc::listxattr(p.as_ptr(), buf as *mut libc::c_char, size, options(follow))
+    });
+}
+
+#[cfg(target_os = "macos")]
+fn get(p: &CString, name: &CString, follow: bool) -> io::Result<Vec<u8>> {
+    return read_sized(|buf, size| unsafe {
+        libc::getxattr(p.as_ptr(), name.as_ptr(), buf, size, 0, options(follow))
+    });
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn list(_p: &CString, _follow: bool) -> io::Result<Vec<u8>> {
+    return Err(io::Error::from_raw_os_error(libc::ENOTSUP));
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn get(_p: &CString, _name: &CString, _follow: bool) -> io::Result<Vec<u8>> {
+    return Err(io::Error::from_raw_os_error(libc::ENOTSUP));
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::xattr::Xattrs;
+    use std::ffi::OsString;
+
+    #[test]
+    fn diff() {
+        let x1 = Xattrs::new(vec![
+            (OsString::from("user.tag"), 1),
+            (OsString::from("security.selinux"), 2),
+            (OsString::from("system.posix_acl_access"), 3),
+        ]);
+        let x2 = Xattrs::new(vec![
+            (OsString::from("user.tag"), 1),
+            (OsString::from("security.selinux"), 4),
+            (OsString::from("user.new"), 5),
+        ]);
+        assert!(x1.diff(&x1).is_empty());
+        assert_eq!(
+            x1.diff(&x2),
+            vec![
+                OsString::from("security.selinux"),
+                OsString::from("system.posix_acl_access"),
+                OsString::from("user.new"),
+            ]
+        );
+    }
+}